@@ -0,0 +1,614 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Detection and extraction of the file formats rule sources are served as.
+
+use anyhow::{Context, Result};
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use tar::Archive;
+use zip::ZipArchive;
+
+/// Number of leading bytes of a download needed to detect its format.
+pub const DETECT_LEN: usize = 8192;
+
+#[derive(Debug)]
+pub struct SourceFile {
+    pub filename: String,
+    pub content: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Rules,
+    RulesGz,
+    Tar,
+    TarGz,
+    TarBz2,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 6] = [
+        Self::Rules,
+        Self::RulesGz,
+        Self::Tar,
+        Self::TarGz,
+        Self::TarBz2,
+        Self::Zip,
+    ];
+
+    /// The file extension, used to preserve the format in cache filenames.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Rules => "rules",
+            Self::RulesGz => "rules.gz",
+            Self::Tar => "tar",
+            Self::TarGz => "tar.gz",
+            Self::TarBz2 => "tar.bz2",
+            Self::Zip => "zip",
+        }
+    }
+
+    /// Whether the format is an archive of files, rather than a single
+    /// rule file.
+    pub fn is_archive(self) -> bool {
+        !matches!(self, Self::Rules | Self::RulesGz)
+    }
+
+    /// The format of a file by its extension.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let filename = filename.to_ascii_lowercase();
+        if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if filename.ends_with(".tar.bz2") || filename.ends_with(".tbz2") {
+            Some(Self::TarBz2)
+        } else if filename.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if filename.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if filename.ends_with(".rules.gz") {
+            Some(Self::RulesGz)
+        } else if filename.ends_with(".rules") {
+            Some(Self::Rules)
+        } else {
+            None
+        }
+    }
+
+    /// The format of a download by the extension of the URL path.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        Self::from_filename(path)
+    }
+
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        match mime.as_str() {
+            "application/zip" | "application/x-zip-compressed" => Some(Self::Zip),
+            "application/x-tar" => Some(Self::Tar),
+            "application/x-bzip2" | "application/x-bzip-compressed-tar" => Some(Self::TarBz2),
+            "application/x-gtar" | "application/x-compressed-tar" | "application/x-tgz" => {
+                Some(Self::TarGz)
+            }
+            "text/plain" => Some(Self::Rules),
+            _ => None,
+        }
+    }
+
+    /// The format identified by the leading bytes of the content, if it is
+    /// an archive or compressed. Gzip compressed content is a tar archive
+    /// if the decompressed content has a tar header.
+    pub fn from_magic(content: &[u8]) -> Option<Self> {
+        if content.starts_with(b"PK\x03\x04") || content.starts_with(b"PK\x05\x06") {
+            Some(Self::Zip)
+        } else if content.starts_with(b"BZh") {
+            Some(Self::TarBz2)
+        } else if content.starts_with(&[0x1f, 0x8b]) {
+            let mut header = Vec::with_capacity(512);
+            let _ = GzDecoder::new(content).take(512).read_to_end(&mut header);
+            if is_tar_header(&header) {
+                Some(Self::TarGz)
+            } else {
+                Some(Self::RulesGz)
+            }
+        } else if is_tar_header(content) {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// Detect the format of downloaded content. The content itself is
+    /// trusted over the URL and Content-Type, except to tell a gzip
+    /// compressed tar archive from a gzip compressed rule file. Content
+    /// that is not recognized is taken to be a plain rule file unless the
+    /// URL or Content-Type say otherwise.
+    pub fn detect(url: &str, content_type: Option<&str>, content: &[u8]) -> Self {
+        let declared =
+            Self::from_url(url).or_else(|| content_type.and_then(Self::from_content_type));
+        match (Self::from_magic(content), declared) {
+            (Some(Self::TarGz | Self::RulesGz), Some(declared @ (Self::TarGz | Self::RulesGz))) => {
+                declared
+            }
+            (Some(format), _) => format,
+            (None, Some(declared)) => declared,
+            (None, None) => Self::Rules,
+        }
+    }
+}
+
+/// Tar headers have "ustar" at offset 257 (POSIX and GNU formats).
+fn is_tar_header(content: &[u8]) -> bool {
+    content.get(257..262) == Some(b"ustar")
+}
+
+/// Where the files of a source are read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceLocation {
+    /// A downloaded or local archive, or single rule file.
+    Archive {
+        path: PathBuf,
+        format: ArchiveFormat,
+    },
+    /// A local directory of rule files and their dependencies.
+    Directory(PathBuf),
+}
+
+impl SourceLocation {
+    /// A cached download, whose format is kept in its filename.
+    pub fn cached(path: PathBuf) -> Self {
+        let format =
+            ArchiveFormat::from_filename(&path.to_string_lossy()).unwrap_or(ArchiveFormat::TarGz);
+        Self::Archive { path, format }
+    }
+
+    /// A local file or directory. The format of a file is detected from its
+    /// name, or its content if the name has no known extension.
+    pub fn local(path: &Path) -> Result<Self> {
+        let metadata =
+            fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if metadata.is_dir() {
+            return Ok(Self::Directory(path.to_path_buf()));
+        }
+
+        let format = match ArchiveFormat::from_filename(&path.to_string_lossy()) {
+            Some(format) => format,
+            None => {
+                let mut head = Vec::with_capacity(DETECT_LEN);
+                fs::File::open(path)
+                    .and_then(|file| file.take(DETECT_LEN as u64).read_to_end(&mut head))
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                ArchiveFormat::from_magic(&head).unwrap_or(ArchiveFormat::Rules)
+            }
+        };
+        Ok(Self::Archive {
+            path: path.to_path_buf(),
+            format,
+        })
+    }
+
+    /// Whether the source may have files other than rule files, such as
+    /// datasets.
+    pub fn has_dependencies(&self) -> bool {
+        match self {
+            Self::Archive { format, .. } => format.is_archive(),
+            Self::Directory(_) => true,
+        }
+    }
+
+    /// Read the files of the source one at a time, see [`for_each_file`].
+    pub fn for_each_file<P, F>(&self, filename: &str, filter: P, f: F) -> Result<()>
+    where
+        P: FnMut(&str) -> bool,
+        F: FnMut(SourceFile) -> Result<()>,
+    {
+        match self {
+            Self::Archive { path, format } => for_each_file(path, *format, filename, filter, f),
+            Self::Directory(dir) => for_each_dir_file(dir, filter, f),
+        }
+    }
+}
+
+/// Extract the files from an archive. Single rule files, which carry no
+/// filename of their own, are returned with the given filename.
+pub fn extract(path: &Path, format: ArchiveFormat, filename: &str) -> Result<Vec<SourceFile>> {
+    let mut files = Vec::new();
+    for_each_file(
+        path,
+        format,
+        filename,
+        |_| true,
+        |file| {
+            files.push(file);
+            Ok(())
+        },
+    )?;
+    Ok(files)
+}
+
+/// Read the files of an archive one at a time, so only a single file is in
+/// memory. Only files whose name is accepted by `filter` are read, the
+/// others are skipped without reading their content.
+pub fn for_each_file<P, F>(
+    path: &Path,
+    format: ArchiveFormat,
+    filename: &str,
+    mut filter: P,
+    mut f: F,
+) -> Result<()>
+where
+    P: FnMut(&str) -> bool,
+    F: FnMut(SourceFile) -> Result<()>,
+{
+    let mut file =
+        fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    match format {
+        ArchiveFormat::Rules | ArchiveFormat::RulesGz => {
+            if !filter(filename) {
+                return Ok(());
+            }
+            let mut content = Vec::new();
+            if format == ArchiveFormat::RulesGz {
+                GzDecoder::new(file).read_to_end(&mut content)?;
+            } else {
+                file.read_to_end(&mut content)?;
+            }
+            f(SourceFile {
+                filename: filename.to_string(),
+                content,
+            })
+        }
+        ArchiveFormat::Tar => for_each_tar_file(file, filter, f),
+        ArchiveFormat::TarGz => for_each_tar_file(GzDecoder::new(file), filter, f),
+        ArchiveFormat::TarBz2 => for_each_tar_file(BzDecoder::new(file), filter, f),
+        ArchiveFormat::Zip => for_each_zip_file(file, filter, f),
+    }
+}
+
+/// Read the files of a directory recursively, like the files of an archive,
+/// named by their path relative to the directory. Hidden files and
+/// directories are skipped, as are links to directories.
+pub fn for_each_dir_file<P, F>(dir: &Path, mut filter: P, mut f: F) -> Result<()>
+where
+    P: FnMut(&str) -> bool,
+    F: FnMut(SourceFile) -> Result<()>,
+{
+    let mut pending = vec![dir.to_path_buf()];
+    let mut files = Vec::new();
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)
+            .with_context(|| format!("Failed to read directory {}", current.display()))?
+        {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() || (file_type.is_symlink() && path.is_file()) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+
+    for path in files {
+        let filename = path
+            .strip_prefix(dir)
+            .unwrap_or(&path)
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        if !filter(&filename) {
+            continue;
+        }
+        let content =
+            fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        f(SourceFile { filename, content })?;
+    }
+
+    Ok(())
+}
+
+fn for_each_tar_file<R, P, F>(reader: R, mut filter: P, mut f: F) -> Result<()>
+where
+    R: Read,
+    P: FnMut(&str) -> bool,
+    F: FnMut(SourceFile) -> Result<()>,
+{
+    let mut archive = Archive::new(reader);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        // Skip directories, links and other special entries
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let filename = entry.path()?.to_string_lossy().to_string();
+        if !filter(&filename) {
+            continue;
+        }
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+
+        f(SourceFile { filename, content })?;
+    }
+
+    Ok(())
+}
+
+fn for_each_zip_file<P, F>(file: fs::File, mut filter: P, mut f: F) -> Result<()>
+where
+    P: FnMut(&str) -> bool,
+    F: FnMut(SourceFile) -> Result<()>,
+{
+    let mut archive = ZipArchive::new(file)?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+
+        // Skip directories
+        if file.is_dir() {
+            continue;
+        }
+
+        let filename = file.name().to_string();
+        if !filter(&filename) {
+            continue;
+        }
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+
+        f(SourceFile { filename, content })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const RULE: &[u8] = b"alert ip any any -> any any (msg:\"test\"; sid:1;)\n";
+
+    fn tar(content: &[u8]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "rules/test.rules", content)
+            .unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn gzip(content: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn bzip2(content: &[u8]) -> Vec<u8> {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(content).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zip(content: &[u8]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer
+            .start_file("rules/test.rules", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(content).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_format_from_url_and_content_type() {
+        assert_eq!(
+            ArchiveFormat::from_url("https://example.com/rules.tar.gz?token=abc"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_url("https://example.com/rules.TGZ"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_url("https://example.com/emerging.rules.gz"),
+            Some(ArchiveFormat::RulesGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_url("https://example.com/download"),
+            None
+        );
+        assert_eq!(
+            ArchiveFormat::from_content_type("application/zip"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_content_type("text/plain; charset=utf-8"),
+            Some(ArchiveFormat::Rules)
+        );
+        assert_eq!(
+            ArchiveFormat::from_content_type("application/octet-stream"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detect_format_from_content() {
+        let url = "https://example.com/download";
+        assert_eq!(
+            ArchiveFormat::detect(url, None, &tar(RULE)),
+            ArchiveFormat::Tar
+        );
+        assert_eq!(
+            ArchiveFormat::detect(url, None, &gzip(&tar(RULE))),
+            ArchiveFormat::TarGz
+        );
+        assert_eq!(
+            ArchiveFormat::detect(url, None, &gzip(RULE)),
+            ArchiveFormat::RulesGz
+        );
+        assert_eq!(
+            ArchiveFormat::detect(url, None, &bzip2(&tar(RULE))),
+            ArchiveFormat::TarBz2
+        );
+        assert_eq!(
+            ArchiveFormat::detect(url, None, &zip(RULE)),
+            ArchiveFormat::Zip
+        );
+        assert_eq!(ArchiveFormat::detect(url, None, RULE), ArchiveFormat::Rules);
+
+        // The content wins over a misleading URL.
+        assert_eq!(
+            ArchiveFormat::detect("https://example.com/rules.tar.gz", None, &zip(RULE)),
+            ArchiveFormat::Zip
+        );
+    }
+
+    #[test]
+    fn test_for_each_file_filter() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("archive.tar");
+        fs::write(&path, tar(RULE)).unwrap();
+
+        let mut seen = Vec::new();
+        for_each_file(
+            &path,
+            ArchiveFormat::Tar,
+            "source.rules",
+            |name| {
+                seen.push(name.to_string());
+                false
+            },
+            |_| panic!("filtered files are not read"),
+        )
+        .unwrap();
+        assert_eq!(seen, vec!["rules/test.rules"]);
+
+        // Single rule files are filtered by the given filename.
+        let path = root.join("source.rules");
+        fs::write(&path, RULE).unwrap();
+        let mut count = 0;
+        for_each_file(
+            &path,
+            ArchiveFormat::Rules,
+            "source.rules",
+            |name| name == "source.rules",
+            |file| {
+                assert_eq!(file.content, RULE);
+                count += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(count, 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_local_source_location() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        fs::create_dir_all(root.join("rules/lists")).unwrap();
+        fs::create_dir_all(root.join("rules/.git")).unwrap();
+        fs::write(root.join("rules/test.rules"), RULE).unwrap();
+        fs::write(root.join("rules/lists/hosts.lst"), "example.com\n").unwrap();
+        fs::write(root.join("rules/.git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        // No extension, detected from the content.
+        fs::write(root.join("download"), gzip(&tar(RULE))).unwrap();
+
+        assert_eq!(
+            SourceLocation::local(&root.join("download")).unwrap(),
+            SourceLocation::Archive {
+                path: root.join("download"),
+                format: ArchiveFormat::TarGz
+            }
+        );
+        assert!(SourceLocation::local(&root.join("missing.rules")).is_err());
+
+        let location = SourceLocation::local(&root.join("rules")).unwrap();
+        assert_eq!(location, SourceLocation::Directory(root.join("rules")));
+        let mut filenames = Vec::new();
+        location
+            .for_each_file(
+                "source.rules",
+                |_| true,
+                |file| {
+                    filenames.push(file.filename);
+                    Ok(())
+                },
+            )
+            .unwrap();
+        assert_eq!(filenames, vec!["lists/hosts.lst", "test.rules"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_extract_formats() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        fs::create_dir_all(&root).unwrap();
+
+        let cases = [
+            (ArchiveFormat::Rules, RULE.to_vec(), "source.rules"),
+            (ArchiveFormat::RulesGz, gzip(RULE), "source.rules"),
+            (ArchiveFormat::Tar, tar(RULE), "rules/test.rules"),
+            (ArchiveFormat::TarGz, gzip(&tar(RULE)), "rules/test.rules"),
+            (ArchiveFormat::TarBz2, bzip2(&tar(RULE)), "rules/test.rules"),
+            (ArchiveFormat::Zip, zip(RULE), "rules/test.rules"),
+        ];
+        for (format, content, expected) in cases {
+            let path = root.join(format!("archive.{}", format.extension()));
+            fs::write(&path, content).unwrap();
+            assert_eq!(
+                ArchiveFormat::from_filename(&path.to_string_lossy()),
+                Some(format)
+            );
+
+            let files = extract(&path, format, "source.rules").unwrap();
+            assert_eq!(files.len(), 1, "{format:?}");
+            assert_eq!(files[0].filename, expected);
+            assert_eq!(files[0].content, RULE);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
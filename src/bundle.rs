@@ -0,0 +1,664 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Bundles of the sources index, enabled source configurations and cached
+//! source downloads, to carry into networks without internet access and
+//! run `update --offline` there.
+//!
+//! A bundle is a gzip compressed tarball laid out as:
+//!
+//! ```text
+//! manifest.yaml
+//! index.yaml
+//! sources/<name>.yaml
+//! cache/<file>
+//! cache/<file>.sig
+//! ```
+//!
+//! The source configurations are bundled without their secrets, parameter
+//! values are masked and HTTP headers removed. The downloads are bundled
+//! under the cache key the redacted configuration resolves to, which is all
+//! an offline update needs to find them. The downloads of sources with a
+//! public key are bundled with their signatures, which are verified on
+//! import and again by the offline update.
+
+use crate::http::CacheValidators;
+use crate::paths::PathProvider;
+use crate::rulesets::{EnabledSource, RulesetManager};
+use crate::sources::SourceManager;
+use crate::update::UpdateManager;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use tar::{Archive, Builder, Header};
+
+const MANIFEST_FILE: &str = "manifest.yaml";
+const INDEX_FILE: &str = "index.yaml";
+const SOURCES_DIR: &str = "sources";
+const CACHE_DIR: &str = "cache";
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub version: u32,
+    pub created: String,
+    /// The Suricata version source URLs were resolved with. The target must
+    /// resolve the same URLs to find the cached downloads.
+    #[serde(rename = "suricata-version")]
+    pub suricata_version: String,
+    pub sources: Vec<BundledSource>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundledSource {
+    pub name: String,
+    pub url: String,
+    pub file: String,
+    /// The signature of the file, for sources with a public key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// Write a bundle of the cached index and the cached downloads of the
+/// enabled sources. Every enabled source must have been downloaded.
+pub fn export_bundle(
+    path_provider: &dyn PathProvider,
+    suricata_version: Option<&str>,
+    output: &Path,
+) -> Result<BundleManifest> {
+    let source_manager = SourceManager::new(path_provider);
+    let index = source_manager.get_index()?.ok_or_else(|| {
+        anyhow::anyhow!("No sources index found, run update-sources before exporting")
+    })?;
+
+    let mut enabled_sources = RulesetManager::new(path_provider).get_enabled_source_configs()?;
+    if enabled_sources.is_empty() {
+        // Match update, which falls back to et/open.
+        enabled_sources = vec![EnabledSource::new("et/open".to_string())];
+    }
+    enabled_sources.sort_by(|a, b| a.source.cmp(&b.source));
+
+    let update_manager = UpdateManager::new_with_suricata_version(path_provider, suricata_version);
+    let mut manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        created: chrono::Utc::now().to_rfc3339(),
+        suricata_version: update_manager.suricata_version().to_string(),
+        sources: Vec::new(),
+    };
+
+    let mut redacted_sources: Vec<EnabledSource> = enabled_sources
+        .iter()
+        .map(EnabledSource::redacted)
+        .collect();
+    let mut cache_files = Vec::new();
+    for (enabled_source, redacted) in enabled_sources.iter().zip(&mut redacted_sources) {
+        let source_info = enabled_source.source_info(&index).ok_or_else(|| {
+            anyhow::anyhow!("Source {} not found in index", enabled_source.source)
+        })?;
+        // The sensors may not have the signed index the key came from.
+        if redacted.public_key.is_none() {
+            redacted.public_key = source_info.public_key.clone();
+        }
+        let (url, cache_path) = update_manager.cached_source(&source_info, enabled_source)?;
+        if crate::paths::local_url_path(&url)?.is_some() {
+            eprintln!(
+                "{}: Not bundling local source {}, it must be available at {} on the sensors",
+                "Warning".yellow(),
+                enabled_source.source,
+                url
+            );
+            continue;
+        }
+        let cache_path = cache_path.ok_or_else(|| {
+            anyhow::anyhow!(
+                "No cached download of {} found, run update before exporting",
+                enabled_source.source
+            )
+        })?;
+        // Keep the archive format extension of the cached file.
+        let filename = cache_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = filename
+            .split_once('.')
+            .map(|(_, extension)| extension)
+            .unwrap_or_default();
+        let filename = format!(
+            "{}.{extension}",
+            update_manager.source_cache_key(&source_info, redacted)?
+        );
+        let signature_path = match &redacted.public_key {
+            Some(public_key) => {
+                crate::signature::verify_local_file(public_key, &cache_path).with_context(
+                    || {
+                        format!(
+                            "Cached download of {} can't be verified, run update before exporting",
+                            enabled_source.source
+                        )
+                    },
+                )?;
+                Some(crate::signature::signature_path(&cache_path))
+            }
+            None => None,
+        };
+        manifest.sources.push(BundledSource {
+            name: enabled_source.source.clone(),
+            url,
+            file: format!("{CACHE_DIR}/{filename}"),
+            signature: signature_path
+                .as_ref()
+                .map(|_| crate::signature::signature_url(&format!("{CACHE_DIR}/{filename}"))),
+        });
+        cache_files.push((cache_path, signature_path));
+    }
+
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        crate::paths::ensure_dir_exists(parent)?;
+    }
+    let file = fs::File::create(output)
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+    let mut builder = Builder::new(GzEncoder::new(file, Compression::default()));
+
+    append_bytes(
+        &mut builder,
+        MANIFEST_FILE,
+        serde_yaml::to_string(&manifest)?.as_bytes(),
+    )?;
+    // The merged index, so the sensors don't need the additional indexes.
+    append_bytes(
+        &mut builder,
+        INDEX_FILE,
+        serde_yaml::to_string(&index)?.as_bytes(),
+    )?;
+    for enabled_source in &redacted_sources {
+        append_bytes(
+            &mut builder,
+            &bundled_config_name(&enabled_source.source),
+            serde_yaml::to_string(enabled_source)?.as_bytes(),
+        )?;
+    }
+    for ((cache_path, signature_path), source) in cache_files.iter().zip(&manifest.sources) {
+        builder
+            .append_path_with_name(cache_path, &source.file)
+            .with_context(|| format!("Failed to add {} to bundle", cache_path.display()))?;
+        if let (Some(signature_path), Some(signature)) = (signature_path, &source.signature) {
+            builder
+                .append_path_with_name(signature_path, signature)
+                .with_context(|| format!("Failed to add {} to bundle", signature_path.display()))?;
+        }
+    }
+
+    builder
+        .into_inner()?
+        .finish()
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+
+    Ok(manifest)
+}
+
+fn append_bytes<W: std::io::Write>(
+    builder: &mut Builder<W>,
+    name: &str,
+    data: &[u8],
+) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, name, data)
+        .with_context(|| format!("Failed to add {name} to bundle"))
+}
+
+/// Install the contents of a bundle into the cache and sources directories.
+pub fn import_bundle(path_provider: &dyn PathProvider, input: &Path) -> Result<BundleManifest> {
+    let file =
+        fs::File::open(input).with_context(|| format!("Failed to open {}", input.display()))?;
+    let mut archive = Archive::new(GzDecoder::new(file));
+
+    // Read everything before installing anything, so an invalid bundle
+    // leaves the existing files untouched.
+    let mut manifest = None;
+    let mut files: Vec<(String, PathBuf, Vec<u8>)> = Vec::new();
+    for entry in archive
+        .entries()
+        .with_context(|| format!("Failed to read bundle {}", input.display()))?
+    {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+
+        if name == MANIFEST_FILE {
+            let parsed: BundleManifest = serde_yaml::from_slice(&content)
+                .with_context(|| format!("Invalid bundle manifest in {}", input.display()))?;
+            manifest = Some(parsed);
+            continue;
+        }
+
+        match bundle_target(path_provider, &name) {
+            Some(target) => files.push((name, target, content)),
+            None => eprintln!(
+                "{}: Ignoring unexpected file {} in bundle",
+                "Warning".yellow(),
+                name
+            ),
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| {
+        anyhow::anyhow!("{} is not a bundle, it has no manifest", input.display())
+    })?;
+    if manifest.version > BUNDLE_VERSION {
+        return Err(anyhow::anyhow!(
+            "Bundle version {} is not supported, upgrade to import it",
+            manifest.version
+        ));
+    }
+
+    verify_bundled_sources(path_provider, &manifest, &files)?;
+
+    for (_, target, content) in &files {
+        if let Some(parent) = target.parent() {
+            crate::paths::ensure_dir_exists(parent)?;
+        }
+        fs::write(target, content)
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+        // Validators of a previous download no longer describe this file.
+        CacheValidators::remove(target)?;
+    }
+
+    Ok(manifest)
+}
+
+/// The name of the configuration of a source in a bundle.
+fn bundled_config_name(source: &str) -> String {
+    format!(
+        "{SOURCES_DIR}/{}.yaml",
+        RulesetManager::safe_filename(source)
+    )
+}
+
+/// Verify the bundled downloads of sources with a public key against their
+/// bundled signatures. A public key already configured for a source can't be
+/// changed or removed by a bundle, and every bundled source configuration
+/// must be for a source listed in the manifest.
+fn verify_bundled_sources(
+    path_provider: &dyn PathProvider,
+    manifest: &BundleManifest,
+    files: &[(String, PathBuf, Vec<u8>)],
+) -> Result<()> {
+    let content = |name: &str| {
+        files
+            .iter()
+            .find(|(file, _, _)| file == name)
+            .map(|(_, _, content)| content)
+    };
+    let configured = RulesetManager::new(path_provider).get_enabled_source_configs()?;
+
+    for (name, _, _) in files {
+        let listed = manifest
+            .sources
+            .iter()
+            .any(|source| bundled_config_name(&source.name) == *name);
+        if name.starts_with(&format!("{SOURCES_DIR}/")) && !listed {
+            return Err(anyhow::anyhow!(
+                "Bundle has source configuration {name} not listed in its manifest, refusing to import it"
+            ));
+        }
+    }
+
+    for source in &manifest.sources {
+        let config_name = bundled_config_name(&source.name);
+        let bundled_key = match content(&config_name) {
+            Some(config) => {
+                let config =
+                    serde_yaml::from_slice::<EnabledSource>(config).with_context(|| {
+                        format!("Invalid source configuration {config_name} in bundle")
+                    })?;
+                if config.source != source.name {
+                    return Err(anyhow::anyhow!(
+                        "Bundled source configuration {config_name} is for {}, not {}",
+                        config.source,
+                        source.name
+                    ));
+                }
+                config.public_key
+            }
+            None => None,
+        };
+        let configured_key = configured
+            .iter()
+            .find(|enabled_source| enabled_source.source == source.name)
+            .and_then(|enabled_source| enabled_source.public_key.clone());
+        if configured_key.is_some() && bundled_key != configured_key {
+            return Err(anyhow::anyhow!(
+                "Bundle changes the public key of {}, refusing to import it",
+                source.name
+            ));
+        }
+
+        let Some(public_key) = bundled_key else {
+            continue;
+        };
+        let data = content(&source.file)
+            .ok_or_else(|| anyhow::anyhow!("Bundle is missing {}", source.file))?;
+        let signature = source
+            .signature
+            .as_deref()
+            .and_then(content)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Bundle has no signature of the download of {}", source.name)
+            })?;
+        crate::signature::verify(&public_key, data, &String::from_utf8_lossy(signature))
+            .with_context(|| format!("Refusing bundled download of {}", source.name))?;
+    }
+
+    Ok(())
+}
+
+/// Where a file from a bundle is installed. Only plain filenames in the
+/// expected directories are accepted.
+fn bundle_target(path_provider: &dyn PathProvider, name: &str) -> Option<PathBuf> {
+    let components: Vec<&str> = Path::new(name)
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+
+    match components.as_slice() {
+        [INDEX_FILE] => Some(path_provider.cache_dir().join(INDEX_FILE)),
+        [SOURCES_DIR, filename] if filename.ends_with(".yaml") => {
+            Some(path_provider.sources_dir().join(filename))
+        }
+        [CACHE_DIR, filename] => Some(path_provider.cache_dir().join(filename)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct TestPaths {
+        root: PathBuf,
+    }
+
+    impl PathProvider for TestPaths {
+        fn sources_dir(&self) -> PathBuf {
+            self.root.join("sources")
+        }
+
+        fn cache_dir(&self) -> PathBuf {
+            self.root.join("cache")
+        }
+
+        fn rules_dir(&self) -> PathBuf {
+            self.root.join("rules")
+        }
+
+        fn config_dir(&self) -> PathBuf {
+            self.root.join("config")
+        }
+    }
+
+    #[test]
+    fn test_export_and_import_bundle() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let online = TestPaths {
+            root: root.join("online"),
+        };
+        let offline = TestPaths {
+            root: root.join("offline"),
+        };
+
+        let url = "https://example.com/%(__version__)s/%(secret-code)s/test.rules";
+        let resolved = "https://example.com/7.0.3/secret/test.rules";
+        let masked = "https://example.com/7.0.3/***/test.rules";
+        fs::create_dir_all(online.cache_dir()).unwrap();
+        fs::write(
+            online.cache_dir().join(INDEX_FILE),
+            format!(
+                "version: 1\nsources:\n  test/rules:\n    vendor: Test\n    summary: Test\n    url: {url}\n"
+            ),
+        )
+        .unwrap();
+        let cache_file = format!("{:x}.rules", md5::compute(resolved.as_bytes()));
+        fs::write(online.cache_dir().join(&cache_file), "cached rules").unwrap();
+        fs::create_dir_all(online.sources_dir()).unwrap();
+        fs::write(
+            online.sources_dir().join("test-rules.yaml"),
+            "source: test/rules\nparams:\n  secret-code: secret\nhttp-header: \"Authorization: Bearer token\"\n",
+        )
+        .unwrap();
+
+        let bundle = root.join("bundle.tar.gz");
+        let manifest = export_bundle(&online, Some("7.0.3"), &bundle).unwrap();
+        assert_eq!(manifest.suricata_version, "7.0.3");
+        assert_eq!(manifest.sources.len(), 1);
+        assert_eq!(manifest.sources[0].url, masked);
+
+        // The secrets stay behind, the download is bundled under the cache
+        // key of the redacted configuration.
+        let imported = import_bundle(&offline, &bundle).unwrap();
+        let bundled_file = format!("{:x}.rules", md5::compute(masked.as_bytes()));
+        assert_eq!(imported.sources[0].file, format!("cache/{bundled_file}"));
+        assert_eq!(
+            fs::read_to_string(offline.cache_dir().join(&bundled_file)).unwrap(),
+            "cached rules"
+        );
+        assert!(offline.cache_dir().join(INDEX_FILE).exists());
+        let source_config =
+            fs::read_to_string(offline.sources_dir().join("test-rules.yaml")).unwrap();
+        assert!(!source_config.contains("secret-code: secret"));
+        assert!(!source_config.contains("Bearer"));
+
+        // An offline update finds the bundled download.
+        let enabled_sources = RulesetManager::new(&offline)
+            .get_enabled_source_configs()
+            .unwrap();
+        assert_eq!(enabled_sources.len(), 1);
+        let index = SourceManager::new(&offline).get_index().unwrap().unwrap();
+        let source_info = enabled_sources[0].source_info(&index).unwrap();
+        let (_, cache_path) = UpdateManager::new_with_suricata_version(&offline, Some("7.0.3"))
+            .cached_source(&source_info, &enabled_sources[0])
+            .unwrap();
+        assert_eq!(cache_path, Some(offline.cache_dir().join(&bundled_file)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_import_bundle_verifies_signatures() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let online = TestPaths {
+            root: root.join("online"),
+        };
+        let public_key = crate::signature::tests::PUBLIC_KEY;
+
+        let url = "https://example.com/test.rules";
+        fs::create_dir_all(online.cache_dir()).unwrap();
+        fs::write(
+            online.cache_dir().join(INDEX_FILE),
+            format!(
+                "version: 1\nsources:\n  test/rules:\n    vendor: Test\n    summary: Test\n    url: {url}\n"
+            ),
+        )
+        .unwrap();
+        let cache_file = format!(
+            "{:x}.rules",
+            md5::compute(format!("{url}\n{public_key}").as_bytes())
+        );
+        fs::write(online.cache_dir().join(&cache_file), "test").unwrap();
+        fs::create_dir_all(online.sources_dir()).unwrap();
+        fs::write(
+            online.sources_dir().join("test-rules.yaml"),
+            format!("source: test/rules\npublic-key: {public_key}\n"),
+        )
+        .unwrap();
+
+        // Without the signature of the cached download there is no bundle.
+        let bundle = root.join("bundle.tar.gz");
+        assert!(export_bundle(&online, Some("7.0.3"), &bundle).is_err());
+        fs::write(
+            online.cache_dir().join(format!("{cache_file}.sig")),
+            crate::signature::tests::SIGNATURE,
+        )
+        .unwrap();
+        let manifest = export_bundle(&online, Some("7.0.3"), &bundle).unwrap();
+        assert_eq!(
+            manifest.sources[0].signature,
+            Some(format!("cache/{cache_file}.sig"))
+        );
+
+        let offline = TestPaths {
+            root: root.join("offline"),
+        };
+        import_bundle(&offline, &bundle).unwrap();
+        assert!(offline
+            .cache_dir()
+            .join(format!("{cache_file}.sig"))
+            .exists());
+
+        // A bundle with a modified download is refused.
+        let tampered = root.join("tampered.tar.gz");
+        rewrite_bundle(&bundle, &tampered, |name, content| {
+            if name.ends_with(".rules") {
+                b"tampered".to_vec()
+            } else {
+                content
+            }
+        });
+        let offline = TestPaths {
+            root: root.join("tampered"),
+        };
+        let err = import_bundle(&offline, &tampered).unwrap_err();
+        assert!(format!("{err:#}").contains("Refusing bundled download of test/rules"));
+        assert!(!offline.cache_dir().exists());
+
+        // A bundle can't drop the public key configured on the sensor.
+        let unsigned = root.join("unsigned.tar.gz");
+        rewrite_bundle(&bundle, &unsigned, |name, content| {
+            if name.starts_with("sources/") {
+                b"source: test/rules\n".to_vec()
+            } else {
+                content
+            }
+        });
+        let offline = TestPaths {
+            root: root.join("offline"),
+        };
+        let err = import_bundle(&offline, &unsigned).unwrap_err();
+        assert!(err.to_string().contains("changes the public key"));
+
+        // Nor can it carry source configurations the manifest doesn't list.
+        let unlisted = root.join("unlisted.tar.gz");
+        rewrite_bundle(&bundle, &unlisted, |name, content| {
+            if name == MANIFEST_FILE {
+                let mut manifest: BundleManifest = serde_yaml::from_slice(&content).unwrap();
+                manifest.sources.clear();
+                serde_yaml::to_string(&manifest).unwrap().into_bytes()
+            } else {
+                content
+            }
+        });
+        let offline = TestPaths {
+            root: root.join("unlisted"),
+        };
+        let err = import_bundle(&offline, &unlisted).unwrap_err();
+        assert!(err.to_string().contains("not listed in its manifest"));
+        assert!(!offline.sources_dir().exists());
+
+        let renamed = root.join("renamed.tar.gz");
+        rewrite_bundle(&bundle, &renamed, |name, content| {
+            if name.starts_with("sources/") {
+                b"source: other/rules\n".to_vec()
+            } else {
+                content
+            }
+        });
+        let err = import_bundle(&offline, &renamed).unwrap_err();
+        assert!(err.to_string().contains("is for other/rules"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Copy a bundle, changing the content of its files.
+    fn rewrite_bundle(input: &Path, output: &Path, rewrite: impl Fn(&str, Vec<u8>) -> Vec<u8>) {
+        let mut archive = Archive::new(GzDecoder::new(fs::File::open(input).unwrap()));
+        let mut builder = Builder::new(GzEncoder::new(
+            fs::File::create(output).unwrap(),
+            Compression::default(),
+        ));
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().to_string();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).unwrap();
+            append_bytes(&mut builder, &name, &rewrite(&name, content)).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_export_bundle_requires_cached_sources() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+        fs::write(
+            paths.cache_dir().join(INDEX_FILE),
+            "version: 1\nsources:\n  et/open:\n    vendor: Test\n    summary: Test\n    url: https://example.com/rules.tar.gz\n",
+        )
+        .unwrap();
+
+        let err = export_bundle(&paths, Some("7.0.3"), &root.join("bundle.tar.gz")).unwrap_err();
+        assert!(err.to_string().contains("run update before exporting"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_bundle_target_rejects_unexpected_paths() {
+        let paths = TestPaths {
+            root: PathBuf::from("/target"),
+        };
+        assert_eq!(
+            bundle_target(&paths, "cache/abc.tar.gz"),
+            Some(PathBuf::from("/target/cache/abc.tar.gz"))
+        );
+        assert_eq!(
+            bundle_target(&paths, "sources/et-open.yaml"),
+            Some(PathBuf::from("/target/sources/et-open.yaml"))
+        );
+        assert_eq!(bundle_target(&paths, "cache/../../etc/passwd"), None);
+        assert_eq!(bundle_target(&paths, "/cache/abc.tar.gz"), None);
+        assert_eq!(bundle_target(&paths, "cache/sub/abc.tar.gz"), None);
+        assert_eq!(bundle_target(&paths, "sources/et-open.txt"), None);
+    }
+}
@@ -1,13 +1,21 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 // SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
 
+use crate::bundle;
+use crate::config::UpdateConfig;
+use crate::http::HttpOptions;
+use crate::local::LocalRulesManager;
 use crate::paths::PathProvider;
+use crate::reload::DEFAULT_COMMAND_SOCKET;
 use crate::rulesets::RulesetManager;
-use crate::sources::SourceManager;
-use crate::update::UpdateManager;
+use crate::sources::{self, SourceFilter, SourceInfo, SourceListing, SourceManager};
+use crate::update::{UpdateManager, UpdateOptions};
 use anyhow::Result;
 use clap::builder::styling::{AnsiColor, Color, Style};
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "suricasta-rules")]
@@ -33,6 +41,46 @@ pub struct Cli {
     )]
     pub verbose: u8,
 
+    #[arg(
+        long,
+        global = true,
+        help = "Proxy URL for all downloads (defaults to HTTPS_PROXY/HTTP_PROXY, honors NO_PROXY)"
+    )]
+    pub proxy: Option<String>,
+
+    #[arg(
+        long = "ca-cert",
+        global = true,
+        help = "PEM file of additional CA certificates to trust"
+    )]
+    pub ca_cert: Option<PathBuf>,
+
+    #[arg(
+        long = "connect-timeout",
+        global = true,
+        value_name = "SECONDS",
+        help = "Connect timeout for downloads"
+    )]
+    pub connect_timeout: Option<u64>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECONDS",
+        help = "Total timeout for each download, including reading the response [default: 30]"
+    )]
+    pub timeout: Option<u64>,
+
+    #[arg(long, global = true, help = "Don't verify TLS certificates (insecure)")]
+    pub insecure: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Number of times to retry failed downloads [default: 3]"
+    )]
+    pub retries: Option<u32>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -40,40 +88,36 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     #[command(about = "Update rule sources and rulesets")]
-    Update {
+    Update(Box<UpdateArgs>),
+
+    #[command(about = "Enable a ruleset")]
+    EnableRuleset {
+        #[arg(help = "Name of the ruleset to enable")]
+        name: Option<String>,
+        #[arg(
+            long = "param",
+            value_name = "KEY=VALUE",
+            action = ArgAction::Append,
+            value_parser = parse_param,
+            help = "Set a ruleset parameter such as secret-code (prompted for if not given)"
+        )]
+        params: Vec<(String, String)>,
         #[arg(
             short = 'f',
             long = "force",
-            help = "Force download even if cache is recent"
+            help = "Enable the ruleset even if it requires a newer Suricata version"
         )]
         force: bool,
-        #[arg(short = 'q', long = "quiet", help = "Only output warnings and errors")]
-        quiet: bool,
         #[arg(
             long = "suricata-version",
-            help = "Suricata version to use when resolving source URLs (auto-detected from suricata -V, falls back to 7.0.0)"
+            help = "Suricata version to check the ruleset against (auto-detected from suricata -V)"
         )]
         suricata_version: Option<String>,
         #[arg(
-            long = "disable-regex",
-            value_name = "REGEX",
-            action = ArgAction::Append,
-            help = "Remove final rules whose full text matches this regular expression"
-        )]
-        disable_regex: Vec<String>,
-        #[arg(
-            long = "disable-substring",
-            value_name = "TEXT",
-            action = ArgAction::Append,
-            help = "Remove final rules whose full text contains this substring"
+            long = "public-key",
+            help = "Minisign public key to verify the download's .sig signature with, in base64"
         )]
-        disable_substring: Vec<String>,
-    },
-
-    #[command(about = "Enable a ruleset")]
-    EnableRuleset {
-        #[arg(help = "Name of the ruleset to enable")]
-        name: Option<String>,
+        public_key: Option<String>,
     },
     #[command(about = "Disable a ruleset")]
     DisableRuleset {
@@ -82,6 +126,257 @@ pub enum Commands {
     },
     #[command(about = "Update rule sources")]
     UpdateSources,
+    #[command(about = "List the sources available in the index")]
+    ListSources {
+        #[arg(long = "enabled", help = "Only list enabled sources")]
+        enabled: bool,
+        #[arg(
+            long = "all",
+            help = "Include deprecated and obsolete sources",
+            conflicts_with = "enabled"
+        )]
+        all: bool,
+        #[arg(long = "json", help = "Output the sources as JSON")]
+        json: bool,
+        #[arg(help = "Only list sources with this text in their name, vendor, summary or license")]
+        search: Option<String>,
+    },
+    #[command(about = "Export the cached index and sources to a bundle for offline use")]
+    ExportBundle {
+        #[arg(help = "Bundle file to write (.tar.gz)")]
+        file: PathBuf,
+        #[arg(
+            long = "suricata-version",
+            help = "Suricata version of the sensors the bundle is for (auto-detected from suricata -V)"
+        )]
+        suricata_version: Option<String>,
+    },
+    #[command(about = "Import a bundle created with export-bundle, for use with update --offline")]
+    ImportBundle {
+        #[arg(help = "Bundle file to import")]
+        file: PathBuf,
+    },
+    #[command(about = "Add a custom source by URL, for rule feeds not in the index")]
+    AddSource {
+        #[arg(help = "Name of the source")]
+        name: String,
+        #[arg(help = "URL of the rules, a rule file or archive")]
+        url: String,
+        #[arg(
+            long = "http-header",
+            help = "HTTP header to send with the request, as \"Name: value\""
+        )]
+        http_header: Option<String>,
+        #[arg(
+            long = "no-checksum",
+            help = "Don't fetch and verify the .md5 checksum of the download"
+        )]
+        no_checksum: bool,
+        #[arg(
+            long = "public-key",
+            help = "Minisign public key to verify the download's .sig signature with, in base64"
+        )]
+        public_key: Option<String>,
+    },
+    #[command(about = "Remove a source added with add-source, or the configuration of a ruleset")]
+    RemoveSource {
+        #[arg(help = "Name of the source")]
+        name: String,
+    },
+    #[command(about = "Add a source index to merge over the default index")]
+    AddIndex {
+        #[arg(help = "URL or path of the index")]
+        url: String,
+        #[arg(
+            long = "public-key",
+            help = "Minisign public key to verify the index's .sig signature with, in base64"
+        )]
+        public_key: Option<String>,
+    },
+    #[command(about = "Remove a source index added with add-index")]
+    RemoveIndex {
+        #[arg(help = "URL or path of the index, as listed in list-sources")]
+        url: String,
+    },
+    #[command(about = "Add a local rule file or directory to every update")]
+    AddLocal {
+        #[arg(help = "Rule file, or directory of *.rules files")]
+        path: PathBuf,
+    },
+    #[command(about = "Remove a local rule file or directory")]
+    RemoveLocal {
+        #[arg(help = "Rule file or directory previously added with add-local")]
+        path: PathBuf,
+    },
+}
+
+/// Options of the update command.
+#[derive(Args)]
+pub struct UpdateArgs {
+    #[arg(
+        short = 'f',
+        long = "force",
+        help = "Force download even if cache is recent"
+    )]
+    pub force: bool,
+    #[arg(short = 'q', long = "quiet", help = "Only output warnings and errors")]
+    pub quiet: bool,
+    #[arg(
+        long = "offline",
+        conflicts_with = "force",
+        help = "Don't download anything, use the cached index and sources regardless of age"
+    )]
+    pub offline: bool,
+    #[arg(
+        long = "fail-on-source-error",
+        overrides_with = "no_fail_on_source_error",
+        help = "Abort the update if any source fails, instead of using its last cached download"
+    )]
+    pub fail_on_source_error: bool,
+    #[arg(
+        long = "no-fail-on-source-error",
+        overrides_with = "fail_on_source_error",
+        help = "Use the last cached download of a failed source, even if update.yaml sets fail-on-source-error"
+    )]
+    pub no_fail_on_source_error: bool,
+    #[arg(
+        long = "suricata-version",
+        help = "Suricata version to use when resolving source URLs (auto-detected from suricata -V, falls back to 7.0.0)"
+    )]
+    pub suricata_version: Option<String>,
+    #[arg(
+        long = "disable-regex",
+        value_name = "REGEX",
+        action = ArgAction::Append,
+        help = "Remove final rules whose full text matches this regular expression"
+    )]
+    pub disable_regex: Vec<String>,
+    #[arg(
+        long = "disable-substring",
+        value_name = "TEXT",
+        action = ArgAction::Append,
+        help = "Remove final rules whose full text contains this substring"
+    )]
+    pub disable_substring: Vec<String>,
+    #[arg(
+        long = "suricata",
+        value_name = "PATH",
+        help = "Path to the Suricata binary used for version detection and testing"
+    )]
+    pub suricata: Option<PathBuf>,
+    #[arg(
+        long = "suricata-conf",
+        value_name = "PATH",
+        help = "Suricata configuration used to test the rules (default: suricata.yaml in the configuration directory)"
+    )]
+    pub suricata_conf: Option<PathBuf>,
+    #[arg(
+        long = "no-test",
+        help = "Don't test the rules with Suricata before installing them"
+    )]
+    pub no_test: bool,
+    #[arg(
+        long = "reload-command",
+        value_name = "COMMAND",
+        help = "Command to run to reload Suricata when the rules have changed"
+    )]
+    pub reload_command: Option<String>,
+    #[arg(
+        long = "reload-socket",
+        value_name = "PATH",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_COMMAND_SOCKET,
+        help = "Reload Suricata over its command socket when the rules have changed"
+    )]
+    pub reload_socket: Option<PathBuf>,
+    #[arg(
+        long = "report-json",
+        value_name = "PATH",
+        help = "Write a JSON report of added, removed, modified, enabled and disabled rules"
+    )]
+    pub report_json: Option<PathBuf>,
+    #[arg(
+        long = "local",
+        value_name = "PATH",
+        action = ArgAction::Append,
+        help = "Local rule file, or directory of *.rules files, to merge into the output"
+    )]
+    pub local: Vec<PathBuf>,
+    #[arg(
+        short = 'c',
+        long = "config",
+        value_name = "PATH",
+        help = "Configuration file (default: update.yaml in the configuration directory)"
+    )]
+    pub config: Option<PathBuf>,
+    #[arg(
+        short = 'o',
+        long = "output",
+        value_name = "DIR",
+        help = "Directory to write the rules and datasets to"
+    )]
+    pub output: Option<PathBuf>,
+    #[arg(
+        long = "enable-conf",
+        value_name = "PATH",
+        help = "Path to enable.conf"
+    )]
+    pub enable_conf: Option<PathBuf>,
+    #[arg(
+        long = "disable-conf",
+        value_name = "PATH",
+        help = "Path to disable.conf"
+    )]
+    pub disable_conf: Option<PathBuf>,
+    #[arg(long = "drop-conf", value_name = "PATH", help = "Path to drop.conf")]
+    pub drop_conf: Option<PathBuf>,
+    #[arg(
+        long = "modify-conf",
+        value_name = "PATH",
+        help = "Path to modify.conf"
+    )]
+    pub modify_conf: Option<PathBuf>,
+    #[arg(
+        long = "ignore",
+        value_name = "PATTERN",
+        action = ArgAction::Append,
+        help = "Ignore rule files in sources with a filename matching this pattern"
+    )]
+    pub ignore: Vec<String>,
+}
+
+impl UpdateArgs {
+    /// The update options given on the command line, update.yaml fills in
+    /// the rest.
+    fn options(&self, http: &HttpOptions) -> UpdateOptions {
+        UpdateOptions {
+            force: self.force,
+            quiet: self.quiet,
+            offline: self.offline,
+            fail_on_source_error: match (self.fail_on_source_error, self.no_fail_on_source_error) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+            output: self.output.clone(),
+            enable_conf: self.enable_conf.clone(),
+            disable_conf: self.disable_conf.clone(),
+            drop_conf: self.drop_conf.clone(),
+            modify_conf: self.modify_conf.clone(),
+            ignore: self.ignore.clone(),
+            suricata_version: self.suricata_version.clone(),
+            suricata: self.suricata.clone(),
+            suricata_conf: self.suricata_conf.clone(),
+            no_test: self.no_test,
+            reload_command: self.reload_command.clone(),
+            reload_socket: self.reload_socket.clone(),
+            report_json: self.report_json.clone(),
+            local: self.local.clone(),
+            disable_regexes: self.disable_regex.clone(),
+            disable_substrings: self.disable_substring.clone(),
+            http: http.clone(),
+        }
+    }
 }
 
 pub fn run(cli: Cli) -> Result<()> {
@@ -90,27 +385,47 @@ pub fn run(cli: Cli) -> Result<()> {
     let user = cfg!(target_os = "windows") || cli.user;
     let path_provider = crate::paths::get_path_provider(user);
 
-    run_with_path_provider(&cli.command, path_provider.as_ref())
+    let http = HttpOptions {
+        proxy: cli.proxy,
+        ca_cert: cli.ca_cert,
+        connect_timeout: cli.connect_timeout,
+        timeout: cli.timeout,
+        insecure: cli.insecure,
+        retries: cli.retries,
+    };
+    if http.insecure {
+        eprintln!(
+            "{}: TLS certificate verification is disabled",
+            "Warning".yellow()
+        );
+    }
+
+    run_with_http_options(&cli.command, path_provider.as_ref(), &http)
 }
 
 pub fn run_with_path_provider(command: &Commands, path_provider: &dyn PathProvider) -> Result<()> {
+    run_with_http_options(command, path_provider, &HttpOptions::default())
+}
+
+pub fn run_with_http_options(
+    command: &Commands,
+    path_provider: &dyn PathProvider,
+    http: &HttpOptions,
+) -> Result<()> {
     match command {
-        Commands::Update {
+        Commands::Update(args) => {
+            let mut options = args.options(http);
+            UpdateConfig::load(args.config.as_deref(), path_provider)?.apply(&mut options);
+            run_update(path_provider, &options)
+        }
+        Commands::EnableRuleset {
+            name,
+            params,
             force,
-            quiet,
             suricata_version,
-            disable_regex,
-            disable_substring,
-        } => update_rules_with_options(
-            path_provider,
-            *force,
-            *quiet,
-            suricata_version.as_deref(),
-            disable_regex,
-            disable_substring,
-        ),
-        Commands::EnableRuleset { name } => {
-            let source_manager = SourceManager::new(path_provider);
+            public_key,
+        } => {
+            let source_manager = SourceManager::new(path_provider).with_http_options(http.clone());
             let ruleset_manager = RulesetManager::new(path_provider);
 
             let source_index = source_manager.get_or_download_index()?;
@@ -128,7 +443,16 @@ pub fn run_with_path_provider(command: &Commands, path_provider: &dyn PathProvid
                 .get(&source_name)
                 .ok_or_else(|| anyhow::anyhow!("Unknown ruleset: {}", source_name))?;
 
-            ruleset_manager.enable_source(&source_name, Some(source_info))
+            let params: HashMap<String, String> = params.iter().cloned().collect();
+            enable_indexed_source(
+                path_provider,
+                &source_name,
+                source_info,
+                &params,
+                suricata_version.as_deref(),
+                *force,
+                public_key.as_deref(),
+            )
         }
         Commands::DisableRuleset { name } => {
             let ruleset_manager = RulesetManager::new(path_provider);
@@ -143,7 +467,48 @@ pub fn run_with_path_provider(command: &Commands, path_provider: &dyn PathProvid
 
             ruleset_manager.disable_source(&source_name)
         }
-        Commands::UpdateSources => update_sources(path_provider),
+        Commands::UpdateSources => SourceManager::new(path_provider)
+            .with_http_options(http.clone())
+            .update_sources(),
+        Commands::ListSources {
+            enabled,
+            all,
+            json,
+            search,
+        } => {
+            let source_manager = SourceManager::new(path_provider).with_http_options(http.clone());
+            let filter = SourceFilter {
+                enabled: *enabled,
+                all: *all,
+                search: search.clone(),
+            };
+            list_sources(&source_manager, path_provider, &filter, *json)
+        }
+        Commands::ExportBundle {
+            file,
+            suricata_version,
+        } => export_bundle(path_provider, file, suricata_version.as_deref()),
+        Commands::ImportBundle { file } => import_bundle(path_provider, file),
+        Commands::AddSource {
+            name,
+            url,
+            http_header,
+            no_checksum,
+            public_key,
+        } => RulesetManager::new(path_provider).add_source(
+            name,
+            url,
+            http_header.as_deref(),
+            !*no_checksum,
+            public_key.as_deref(),
+        ),
+        Commands::RemoveSource { name } => RulesetManager::new(path_provider).remove_source(name),
+        Commands::AddIndex { url, public_key } => {
+            SourceManager::new(path_provider).add_index(url, public_key.as_deref())
+        }
+        Commands::RemoveIndex { url } => SourceManager::new(path_provider).remove_index(url),
+        Commands::AddLocal { path } => LocalRulesManager::new(path_provider).add_local(path),
+        Commands::RemoveLocal { path } => LocalRulesManager::new(path_provider).remove_local(path),
     }
 }
 
@@ -168,8 +533,28 @@ pub fn update_rules_with_options(
     disable_regexes: &[String],
     disable_substrings: &[String],
 ) -> Result<()> {
-    let update_manager = UpdateManager::new_with_suricata_version(path_provider, suricata_version);
-    update_manager.update(force, quiet, disable_regexes, disable_substrings)
+    run_update(
+        path_provider,
+        &UpdateOptions {
+            force,
+            quiet,
+            suricata_version: suricata_version.map(ToOwned::to_owned),
+            disable_regexes: disable_regexes.to_vec(),
+            disable_substrings: disable_substrings.to_vec(),
+            ..Default::default()
+        },
+    )
+}
+
+pub fn run_update(path_provider: &dyn PathProvider, options: &UpdateOptions) -> Result<()> {
+    let update_manager = UpdateManager::new_with_suricata(
+        path_provider,
+        options.suricata.as_deref(),
+        options.suricata_version.as_deref(),
+    )
+    .with_output_dir(options.output.as_deref())
+    .with_http_options(options.http.clone());
+    update_manager.update_with_options(options)
 }
 
 pub fn update_sources(path_provider: &dyn PathProvider) -> Result<()> {
@@ -178,8 +563,15 @@ pub fn update_sources(path_provider: &dyn PathProvider) -> Result<()> {
 }
 
 pub fn enable_ruleset(path_provider: &dyn PathProvider, name: &str) -> Result<()> {
+    enable_ruleset_with_params(path_provider, name, &HashMap::new())
+}
+
+pub fn enable_ruleset_with_params(
+    path_provider: &dyn PathProvider,
+    name: &str,
+    params: &HashMap<String, String>,
+) -> Result<()> {
     let source_manager = SourceManager::new(path_provider);
-    let ruleset_manager = RulesetManager::new(path_provider);
 
     let source_index = source_manager.get_or_download_index()?;
     let source_info = source_index
@@ -187,7 +579,41 @@ pub fn enable_ruleset(path_provider: &dyn PathProvider, name: &str) -> Result<()
         .get(name)
         .ok_or_else(|| anyhow::anyhow!("Unknown ruleset: {}", name))?;
 
-    ruleset_manager.enable_source(name, Some(source_info))
+    enable_indexed_source(path_provider, name, source_info, params, None, false, None)
+}
+
+/// Enable a source of the index, checking its min-version against the
+/// Suricata version and offering to disable the sources it replaces.
+fn enable_indexed_source(
+    path_provider: &dyn PathProvider,
+    name: &str,
+    source_info: &SourceInfo,
+    params: &HashMap<String, String>,
+    suricata_version: Option<&str>,
+    force: bool,
+    public_key: Option<&str>,
+) -> Result<()> {
+    if let Some(public_key) = public_key {
+        crate::signature::parse_public_key(public_key)?;
+    }
+    if source_info.min_version.is_some() {
+        // Only detect the Suricata version when there is something to check.
+        let update_manager =
+            UpdateManager::new_with_suricata_version(path_provider, suricata_version);
+        RulesetManager::check_min_version(
+            name,
+            source_info,
+            update_manager.suricata_version(),
+            force,
+        )?;
+    }
+
+    let ruleset_manager = RulesetManager::new(path_provider);
+    ruleset_manager.enable_source_with_params(name, Some(source_info), params)?;
+    if let Some(public_key) = public_key {
+        ruleset_manager.set_source_public_key(name, public_key)?;
+    }
+    ruleset_manager.disable_replaced_sources(name, source_info)
 }
 
 pub fn disable_ruleset(path_provider: &dyn PathProvider, name: &str) -> Result<()> {
@@ -200,6 +626,125 @@ pub fn enabled_rulesets(path_provider: &dyn PathProvider) -> Result<Vec<String>>
     ruleset_manager.get_enabled_sources()
 }
 
+pub fn list_sources(
+    source_manager: &SourceManager,
+    path_provider: &dyn PathProvider,
+    filter: &SourceFilter,
+    json: bool,
+) -> Result<()> {
+    let source_index = source_manager.get_or_download_index()?;
+    let ruleset_manager = RulesetManager::new(path_provider);
+    let listings = sources::list_sources(&source_index, &ruleset_manager, filter)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&listings)?);
+        return Ok(());
+    }
+
+    if listings.is_empty() {
+        println!("{}: No sources found", "Info".yellow());
+        return Ok(());
+    }
+
+    for (i, listing) in listings.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_source_listing(listing);
+    }
+    Ok(())
+}
+
+fn print_source_listing(listing: &SourceListing) {
+    let info = &listing.info;
+    let mut flags = Vec::new();
+    if listing.enabled {
+        flags.push("enabled".green());
+    }
+    if listing.disabled {
+        flags.push("disabled".bright_black());
+    }
+    if info.deprecated.is_some() {
+        flags.push("deprecated".yellow());
+    }
+    if info.obsolete.is_some() {
+        flags.push("obsolete".red());
+    }
+    if listing.requires_parameters {
+        flags.push("requires parameters".yellow());
+    }
+    let flags: Vec<String> = flags.iter().map(|flag| format!("[{flag}]")).collect();
+    println!("{} {}", listing.name.cyan().bold(), flags.join(" "));
+
+    println!("  Vendor: {}", info.vendor);
+    println!("  Summary: {}", info.summary);
+    if let Some(license) = &info.license {
+        println!("  License: {license}");
+    }
+    if let Some(min_version) = &info.min_version {
+        println!("  Min version: {min_version}");
+    }
+    if listing.requires_parameters {
+        println!("  Parameters: {}", info.parameter_names().join(", "));
+    }
+    if let Some(index) = &listing.index {
+        println!("  Index: {}", index.bright_black());
+    }
+    if let Some(deprecated) = &info.deprecated {
+        println!("  Deprecated: {}", deprecated.bright_black());
+    }
+    if let Some(obsolete) = &info.obsolete {
+        println!("  Obsolete: {}", obsolete.bright_black());
+    }
+}
+
+pub fn export_bundle(
+    path_provider: &dyn PathProvider,
+    file: &Path,
+    suricata_version: Option<&str>,
+) -> Result<()> {
+    let manifest = bundle::export_bundle(path_provider, suricata_version, file)?;
+    for source in &manifest.sources {
+        println!(
+            "Added {} ({})",
+            source.name.cyan(),
+            source.url.bright_black()
+        );
+    }
+    println!(
+        "{}: Wrote bundle of {} sources for Suricata {} to {}",
+        "Success".green(),
+        manifest.sources.len(),
+        manifest.suricata_version,
+        file.display()
+    );
+    Ok(())
+}
+
+pub fn import_bundle(path_provider: &dyn PathProvider, file: &Path) -> Result<()> {
+    let manifest = bundle::import_bundle(path_provider, file)?;
+    println!(
+        "{}: Imported bundle of {} sources created {}",
+        "Success".green(),
+        manifest.sources.len(),
+        manifest.created
+    );
+    println!(
+        "Run update --offline --suricata-version {} to install the rules",
+        manifest.suricata_version
+    );
+    Ok(())
+}
+
+fn parse_param(value: &str) -> std::result::Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("invalid parameter '{value}', expected KEY=VALUE")),
+    }
+}
+
 pub fn init_logging(verbose: u8) {
     let log_level = match verbose {
         0 => "suricasta_rules=info",
@@ -247,6 +792,7 @@ fn get_styles() -> clap::builder::Styles {
 mod tests {
     use super::{Cli, Commands};
     use clap::Parser;
+    use std::path::PathBuf;
 
     #[test]
     fn test_update_command_parses_disable_filters() {
@@ -263,15 +809,128 @@ mod tests {
         .unwrap();
 
         match cli.command {
-            Commands::Update {
-                disable_regex,
-                disable_substring,
-                ..
-            } => {
-                assert_eq!(disable_regex, vec!["foo.*bar", r"sid:\s*1001"]);
-                assert_eq!(disable_substring, vec!["drop tcp"]);
+            Commands::Update(args) => {
+                assert_eq!(args.disable_regex, vec!["foo.*bar", r"sid:\s*1001"]);
+                assert_eq!(args.disable_substring, vec!["drop tcp"]);
+            }
+            _ => panic!("expected update command"),
+        }
+    }
+
+    #[test]
+    fn test_update_command_parses_reload_socket() {
+        let cli = Cli::try_parse_from(["suricasta-rules", "update", "--reload-socket"]).unwrap();
+        match cli.command {
+            Commands::Update(args) => {
+                assert_eq!(
+                    args.reload_socket,
+                    Some(PathBuf::from(super::DEFAULT_COMMAND_SOCKET))
+                );
+            }
+            _ => panic!("expected update command"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "suricasta-rules",
+            "update",
+            "--reload-socket",
+            "/run/suricata.socket",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Update(args) => {
+                assert_eq!(
+                    args.reload_socket,
+                    Some(PathBuf::from("/run/suricata.socket"))
+                );
             }
             _ => panic!("expected update command"),
         }
     }
+
+    #[test]
+    fn test_update_command_parses_local() {
+        let cli = Cli::try_parse_from([
+            "suricasta-rules",
+            "update",
+            "--local",
+            "/etc/suricata/local.rules",
+            "--local",
+            "/etc/suricata/rules.d",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Update(args) => {
+                assert_eq!(
+                    args.local,
+                    vec![
+                        PathBuf::from("/etc/suricata/local.rules"),
+                        PathBuf::from("/etc/suricata/rules.d")
+                    ]
+                );
+            }
+            _ => panic!("expected update command"),
+        }
+    }
+
+    #[test]
+    fn test_http_options_are_global() {
+        let cli = Cli::try_parse_from([
+            "suricasta-rules",
+            "update",
+            "--proxy",
+            "http://proxy.example.com:3128",
+            "--ca-cert",
+            "/etc/ssl/proxy-ca.pem",
+            "--timeout",
+            "120",
+        ])
+        .unwrap();
+        assert_eq!(cli.proxy.as_deref(), Some("http://proxy.example.com:3128"));
+        assert_eq!(cli.ca_cert, Some(PathBuf::from("/etc/ssl/proxy-ca.pem")));
+        assert_eq!(cli.timeout, Some(120));
+        assert_eq!(cli.connect_timeout, None);
+        assert!(!cli.insecure);
+    }
+
+    #[test]
+    fn test_enable_ruleset_parses_params() {
+        let cli = Cli::try_parse_from([
+            "suricasta-rules",
+            "enable-ruleset",
+            "et/pro",
+            "--param",
+            "secret-code=abc=123",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Commands::EnableRuleset {
+                name,
+                params,
+                force,
+                suricata_version,
+                public_key,
+            } => {
+                assert_eq!(name.as_deref(), Some("et/pro"));
+                assert_eq!(
+                    params,
+                    vec![("secret-code".to_string(), "abc=123".to_string())]
+                );
+                assert!(!force);
+                assert_eq!(suricata_version, None);
+                assert_eq!(public_key, None);
+            }
+            _ => panic!("expected enable-ruleset command"),
+        }
+
+        assert!(Cli::try_parse_from([
+            "suricasta-rules",
+            "enable-ruleset",
+            "et/pro",
+            "--param",
+            "secret-code"
+        ])
+        .is_err());
+    }
 }
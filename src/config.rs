@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Suricata-Update style update.yaml configuration for the update command.
+
+use crate::paths::PathProvider;
+use crate::update::UpdateOptions;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const UPDATE_CONF: &str = "update.yaml";
+
+/// Options read from update.yaml. Options given on the command line take
+/// precedence over the values in the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct UpdateConfig {
+    /// Directory to write the rules and datasets to.
+    pub output: Option<PathBuf>,
+    pub enable_conf: Option<PathBuf>,
+    pub disable_conf: Option<PathBuf>,
+    pub drop_conf: Option<PathBuf>,
+    pub modify_conf: Option<PathBuf>,
+    #[serde(default)]
+    pub local: Vec<PathBuf>,
+    pub suricata: Option<PathBuf>,
+    pub suricata_conf: Option<PathBuf>,
+    pub reload_command: Option<String>,
+    pub reload_socket: Option<PathBuf>,
+    #[serde(default)]
+    pub disable_regex: Vec<String>,
+    #[serde(default)]
+    pub disable_substring: Vec<String>,
+    /// Filename patterns of rule files in sources to ignore.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Proxy URL for downloads.
+    pub proxy: Option<String>,
+    /// PEM file of additional CA certificates to trust.
+    pub ca_cert: Option<PathBuf>,
+    /// Connect timeout for downloads in seconds.
+    pub connect_timeout: Option<u64>,
+    /// Total timeout for each download in seconds.
+    pub timeout: Option<u64>,
+    /// Number of times to retry failed downloads.
+    pub retries: Option<u32>,
+    /// Abort the update if any source fails.
+    pub fail_on_source_error: Option<bool>,
+}
+
+impl UpdateConfig {
+    /// Load the given configuration file, or update.yaml from the
+    /// configuration directory if it exists.
+    pub fn load(path: Option<&Path>, path_provider: &dyn PathProvider) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let path = path_provider.config_dir().join(UPDATE_CONF);
+                if !path.exists() {
+                    return Ok(Self::default());
+                }
+                path
+            }
+        };
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read configuration {}", path.display()))?;
+        let mut config =
+            Self::parse(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+        if let Some(dir) = path.parent() {
+            config.resolve_paths(dir);
+        }
+        Ok(config)
+    }
+
+    /// Make the relative paths in the configuration relative to the
+    /// directory of the configuration file. A Suricata binary given by name
+    /// alone is still looked up in PATH.
+    fn resolve_paths(&mut self, dir: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        };
+
+        for path in [
+            &mut self.output,
+            &mut self.enable_conf,
+            &mut self.disable_conf,
+            &mut self.drop_conf,
+            &mut self.modify_conf,
+            &mut self.suricata_conf,
+            &mut self.reload_socket,
+            &mut self.ca_cert,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            resolve(path);
+        }
+        self.local.iter_mut().for_each(resolve);
+        if let Some(suricata) = &mut self.suricata {
+            if suricata.components().count() > 1 {
+                resolve(suricata);
+            }
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        // An empty file, or one with only comments, is an empty configuration.
+        if content.lines().all(|line| {
+            let line = line.trim();
+            line.is_empty() || line.starts_with('#')
+        }) {
+            return Ok(Self::default());
+        }
+        Ok(serde_yaml::from_str(content)?)
+    }
+
+    /// Fill in the options not given on the command line from this
+    /// configuration.
+    pub fn apply(self, options: &mut UpdateOptions) {
+        fn fill<T>(option: &mut Option<T>, value: Option<T>) {
+            if option.is_none() {
+                *option = value;
+            }
+        }
+
+        fn fill_list<T>(list: &mut Vec<T>, values: Vec<T>) {
+            if list.is_empty() {
+                *list = values;
+            }
+        }
+
+        fill(&mut options.output, self.output);
+        fill(&mut options.enable_conf, self.enable_conf);
+        fill(&mut options.disable_conf, self.disable_conf);
+        fill(&mut options.drop_conf, self.drop_conf);
+        fill(&mut options.modify_conf, self.modify_conf);
+        fill(&mut options.suricata, self.suricata);
+        fill(&mut options.suricata_conf, self.suricata_conf);
+        fill(&mut options.reload_command, self.reload_command);
+        fill(&mut options.reload_socket, self.reload_socket);
+        fill_list(&mut options.local, self.local);
+        fill_list(&mut options.disable_regexes, self.disable_regex);
+        fill_list(&mut options.disable_substrings, self.disable_substring);
+        fill_list(&mut options.ignore, self.ignore);
+        fill(&mut options.http.proxy, self.proxy);
+        fill(&mut options.http.ca_cert, self.ca_cert);
+        fill(&mut options.http.connect_timeout, self.connect_timeout);
+        fill(&mut options.http.timeout, self.timeout);
+        fill(&mut options.http.retries, self.retries);
+        fill(&mut options.fail_on_source_error, self.fail_on_source_error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_update_config() {
+        let config = UpdateConfig::parse(
+            r#"
+# Comments are allowed.
+output: /srv/rules
+disable-conf: /etc/suricasta/disable.conf
+local:
+  - /etc/suricata/local.rules
+reload-command: systemctl reload suricata
+disable-regex:
+  - "sid:\\s*1001"
+ignore:
+  - "*deleted.rules"
+proxy: http://proxy.example.com:3128
+connect-timeout: 10
+fail-on-source-error: true
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.output, Some(PathBuf::from("/srv/rules")));
+        assert_eq!(
+            config.disable_conf,
+            Some(PathBuf::from("/etc/suricasta/disable.conf"))
+        );
+        assert_eq!(
+            config.local,
+            vec![PathBuf::from("/etc/suricata/local.rules")]
+        );
+        assert_eq!(
+            config.reload_command.as_deref(),
+            Some("systemctl reload suricata")
+        );
+        assert_eq!(config.disable_regex, vec![r"sid:\s*1001"]);
+        assert_eq!(config.ignore, vec!["*deleted.rules"]);
+        assert_eq!(
+            config.proxy.as_deref(),
+            Some("http://proxy.example.com:3128")
+        );
+        assert_eq!(config.connect_timeout, Some(10));
+        assert_eq!(config.fail_on_source_error, Some(true));
+
+        assert!(UpdateConfig::parse("# nothing configured\n")
+            .unwrap()
+            .output
+            .is_none());
+        assert!(UpdateConfig::parse("local: [").is_err());
+    }
+
+    #[test]
+    fn test_command_line_overrides_config() {
+        let config = UpdateConfig {
+            output: Some(PathBuf::from("/srv/rules")),
+            suricata: Some(PathBuf::from("/opt/suricata/bin/suricata")),
+            disable_regex: vec!["from-config".to_string()],
+            ignore: vec!["*deleted.rules".to_string()],
+            fail_on_source_error: Some(true),
+            ..Default::default()
+        };
+        let mut options = UpdateOptions {
+            output: Some(PathBuf::from("/tmp/rules")),
+            disable_regexes: vec!["from-cli".to_string()],
+            fail_on_source_error: Some(false),
+            ..Default::default()
+        };
+
+        config.apply(&mut options);
+
+        assert_eq!(options.output, Some(PathBuf::from("/tmp/rules")));
+        assert_eq!(
+            options.suricata,
+            Some(PathBuf::from("/opt/suricata/bin/suricata"))
+        );
+        assert_eq!(options.disable_regexes, vec!["from-cli"]);
+        assert_eq!(options.ignore, vec!["*deleted.rules"]);
+        assert_eq!(options.fail_on_source_error, Some(false));
+    }
+
+    #[test]
+    fn test_relative_paths_from_config_dir() {
+        let mut config = UpdateConfig::parse(
+            r#"
+output: rules
+disable-conf: /etc/suricasta/disable.conf
+local:
+  - local.rules
+suricata: suricata
+suricata-conf: ../suricata/suricata.yaml
+"#,
+        )
+        .unwrap();
+        config.resolve_paths(Path::new("/etc/suricasta"));
+
+        assert_eq!(config.output, Some(PathBuf::from("/etc/suricasta/rules")));
+        assert_eq!(
+            config.disable_conf,
+            Some(PathBuf::from("/etc/suricasta/disable.conf"))
+        );
+        assert_eq!(
+            config.local,
+            vec![PathBuf::from("/etc/suricasta/local.rules")]
+        );
+        assert_eq!(config.suricata, Some(PathBuf::from("suricata")));
+        assert_eq!(
+            config.suricata_conf,
+            Some(PathBuf::from("/etc/suricasta/../suricata/suricata.yaml"))
+        );
+    }
+}
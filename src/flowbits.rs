@@ -0,0 +1,172 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Flowbit dependency resolution.
+//!
+//! A rule that checks a flowbit can never match if every rule that sets
+//! that flowbit is disabled. Like Suricata-Update, the setter rules
+//! required by enabled rules are enabled, repeating until no more rules
+//! need to be enabled as newly enabled rules may depend on other flowbits.
+
+use crate::update::Rule;
+use std::collections::{HashMap, HashSet};
+
+const CHECK_ACTIONS: &[&str] = &["isset", "isnotset"];
+const SET_ACTIONS: &[&str] = &["set", "unset", "toggle"];
+
+/// Enable the disabled rules that set flowbits checked by enabled rules.
+/// Rules with a key in `protected` (rules explicitly disabled by the user)
+/// are never enabled. Returns the keys of the enabled rules, in the order
+/// they were enabled.
+pub(crate) fn resolve_flowbits(
+    rules: &mut HashMap<String, Rule>,
+    protected: &HashSet<String>,
+) -> Vec<String> {
+    let mut enabled = Vec::new();
+
+    loop {
+        let required = required_flowbits(rules);
+        let mut to_enable: Vec<String> = rules
+            .iter()
+            .filter(|(key, rule)| {
+                !rule.enabled
+                    && !protected.contains(*key)
+                    && flowbits(rule, SET_ACTIONS).any(|name| required.contains(name))
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        if to_enable.is_empty() {
+            break;
+        }
+
+        to_enable.sort();
+        for key in to_enable {
+            if let Some(rule) = rules.get_mut(&key) {
+                enable_as_noalert(rule);
+                enabled.push(key);
+            }
+        }
+    }
+
+    enabled
+}
+
+/// The flowbits checked by enabled rules.
+fn required_flowbits(rules: &HashMap<String, Rule>) -> HashSet<String> {
+    rules
+        .values()
+        .filter(|rule| rule.enabled)
+        .flat_map(|rule| flowbits(rule, CHECK_ACTIONS))
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// The flowbit names used by a rule with one of the given flowbit actions.
+/// Names may be combined with "|" or "&".
+fn flowbits<'r>(rule: &'r Rule, actions: &'r [&str]) -> impl Iterator<Item = &'r str> {
+    rule.option_values("flowbits")
+        .filter_map(move |value| {
+            let (action, names) = value.split_once(',')?;
+            actions.contains(&action.trim()).then_some(names)
+        })
+        .flat_map(|names| names.split(['|', '&']))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+fn is_noalert(rule: &Rule) -> bool {
+    rule.options.iter().any(|option| {
+        option.name == "noalert"
+            || (option.name == "flowbits"
+                && option
+                    .value
+                    .as_deref()
+                    .is_some_and(|value| value.trim() == "noalert"))
+    })
+}
+
+/// Enable a rule, adding "flowbits:noalert" to alert rules so rules that
+/// were disabled only serve to set flowbits and do not generate alerts.
+fn enable_as_noalert(rule: &mut Rule) {
+    rule.enabled = true;
+    if rule.action != "alert" || is_noalert(rule) {
+        return;
+    }
+    if let Some(body) = rule.raw.trim_end().strip_suffix(')') {
+        let raw = format!("{} flowbits:noalert;)", body.trim_end());
+        // The rule only gains a simple option, so this will not fail to parse.
+        let _ = rule.set_raw(raw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(rules: &[(&str, bool)]) -> HashMap<String, Rule> {
+        rules
+            .iter()
+            .map(|(raw, enabled)| {
+                let rule = Rule::parse(raw, *enabled, "rules/test.rules").unwrap();
+                (format!("{}:{}", rule.gid, rule.sid), rule)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_flowbits_to_fixed_point() {
+        let mut rules = rules(&[
+            (
+                "alert tcp any any -> any any (msg:\"checker\"; flowbits:isset,first; sid:1;)",
+                true,
+            ),
+            (
+                "alert tcp any any -> any any (msg:\"first\"; flowbits:isset,second; flowbits:set,first; sid:2;)",
+                false,
+            ),
+            (
+                "drop tcp any any -> any any (msg:\"second\"; flowbits:set,second; sid:3;)",
+                false,
+            ),
+            (
+                "alert tcp any any -> any any (msg:\"unrelated\"; flowbits:set,other; sid:4;)",
+                false,
+            ),
+        ]);
+
+        let enabled = resolve_flowbits(&mut rules, &HashSet::new());
+
+        assert_eq!(enabled, vec!["1:2", "1:3"]);
+        assert!(rules["1:2"].enabled);
+        assert!(rules["1:2"].raw.ends_with("sid:2; flowbits:noalert;)"));
+        assert!(rules["1:3"].enabled);
+        assert!(!rules["1:3"].raw.contains("noalert"));
+        assert!(!rules["1:4"].enabled);
+    }
+
+    #[test]
+    fn test_resolve_flowbits_combined_names_and_protected_rules() {
+        let mut rules = rules(&[
+            (
+                "alert tcp any any -> any any (msg:\"checker\"; flowbits:isset,a|b; sid:1;)",
+                true,
+            ),
+            (
+                "alert tcp any any -> any any (msg:\"a\"; flowbits:set,a; flowbits:noalert; sid:2;)",
+                false,
+            ),
+            (
+                "alert tcp any any -> any any (msg:\"b\"; flowbits:set,b; sid:3;)",
+                false,
+            ),
+        ]);
+
+        let protected = HashSet::from(["1:3".to_string()]);
+        let enabled = resolve_flowbits(&mut rules, &protected);
+
+        assert_eq!(enabled, vec!["1:2"]);
+        assert_eq!(rules["1:2"].raw.matches("noalert").count(), 1);
+        assert!(!rules["1:3"].enabled);
+    }
+}
@@ -0,0 +1,337 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! HTTP helpers shared by the index and source downloads.
+
+use crate::user_agent::UserAgent;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{
+    HeaderMap, HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::{Certificate, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::debug;
+
+/// Number of times a failed request is retried by default.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Delay before the first retry, doubled for each following retry.
+#[cfg(not(test))]
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+#[cfg(test)]
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// Options for the HTTP client used for all downloads.
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    /// Proxy URL for all requests. Without it the HTTP_PROXY, HTTPS_PROXY
+    /// and NO_PROXY environment variables are used.
+    pub proxy: Option<String>,
+    /// PEM file of additional CA certificates to trust, such as the
+    /// certificate of a TLS intercepting proxy.
+    pub ca_cert: Option<PathBuf>,
+    /// Connect timeout in seconds.
+    pub connect_timeout: Option<u64>,
+    /// Timeout in seconds for a whole request, from connecting until the
+    /// response body has been read, 30 seconds by default.
+    pub timeout: Option<u64>,
+    /// Don't verify TLS certificates.
+    pub insecure: bool,
+    /// Number of times to retry a request after a network error or server
+    /// error, DEFAULT_RETRIES if not set.
+    pub retries: Option<u32>,
+}
+
+impl HttpOptions {
+    pub fn build_client(&self) -> Result<Client> {
+        let user_agent = UserAgent::new().to_string();
+        debug!("Using User-Agent: {}", user_agent);
+        let mut builder = Client::builder().user_agent(user_agent);
+
+        if let Some(proxy) = &self.proxy {
+            // An explicit proxy still honors NO_PROXY from the environment.
+            let proxy = Proxy::all(proxy)
+                .with_context(|| format!("Invalid proxy URL: {proxy}"))?
+                .no_proxy(NoProxy::from_env());
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(path) = &self.ca_cert {
+            let pem = fs::read(path)
+                .with_context(|| format!("Failed to read CA certificate {}", path.display()))?;
+            let certs = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid CA certificate {}", path.display()))?;
+            if certs.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "No certificates found in {}",
+                    path.display()
+                ));
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        if let Some(secs) = self.connect_timeout {
+            builder = builder.connect_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.timeout {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+        if self.insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        builder.build().context("Failed to create HTTP client")
+    }
+
+    /// Send a request, retrying network errors and 5xx responses with
+    /// exponential backoff. Other responses are returned as is. Errors don't
+    /// include the URL, which may contain secrets such as an ET Pro code, it
+    /// is up to the caller to name what failed.
+    pub fn send(&self, builder: RequestBuilder) -> reqwest::Result<Response> {
+        let retries = self.retries.unwrap_or(DEFAULT_RETRIES);
+        let mut attempt = 0;
+        loop {
+            // Requests with a streaming body can't be retried.
+            let Some(request) = builder.try_clone() else {
+                return builder.send().map_err(reqwest::Error::without_url);
+            };
+            let result = request.send().map_err(reqwest::Error::without_url);
+            let failure = match &result {
+                Ok(response) if response.status().is_server_error() => {
+                    format!("HTTP {}", response.status())
+                }
+                Ok(_) => return result,
+                Err(err) if err.is_builder() => return result,
+                Err(err) => err.to_string(),
+            };
+            if attempt >= retries {
+                return result;
+            }
+
+            let delay = RETRY_DELAY * 2u32.pow(attempt);
+            attempt += 1;
+            eprintln!(
+                "{}: Request failed ({}), retrying in {:?} ({}/{})",
+                "Warning".yellow(),
+                failure,
+                delay,
+                attempt,
+                retries
+            );
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+/// The ETag and Last-Modified headers of a cached download, stored in a
+/// file next to the cached file so later downloads can be made conditional.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheValidators {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(rename = "last-modified", skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    fn path(cache_path: &Path) -> PathBuf {
+        let mut path = cache_path.as_os_str().to_owned();
+        path.push(".meta.yaml");
+        PathBuf::from(path)
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: HeaderName| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    /// Load the validators for a cached file. They are only used while the
+    /// cached file exists, and a missing or unreadable file means none.
+    pub fn load(cache_path: &Path) -> Self {
+        if !cache_path.exists() {
+            return Self::default();
+        }
+        let path = Self::path(cache_path);
+        fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| match serde_yaml::from_str(&content) {
+                Ok(validators) => Some(validators),
+                Err(err) => {
+                    debug!("Ignoring invalid {}: {}", path.display(), err);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, cache_path: &Path) -> Result<()> {
+        let path = Self::path(cache_path);
+        if self.is_empty() {
+            return Self::remove(cache_path);
+        }
+        let yaml = serde_yaml::to_string(self)?;
+        fs::write(&path, yaml).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn remove(cache_path: &Path) -> Result<()> {
+        let path = Self::path(cache_path);
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Make the request conditional, so the server can respond with 304 Not
+    /// Modified if the cached file is current.
+    pub fn apply(&self, mut builder: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            builder = builder.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            builder = builder.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Serve the given status codes, one connection each, and return the
+    /// URL of the server.
+    fn serve(statuses: Vec<u16>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let _ = write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {status} Status\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+                );
+            }
+        });
+        format!("http://{addr}/")
+    }
+
+    #[test]
+    fn test_build_client() {
+        assert!(HttpOptions::default().build_client().is_ok());
+        assert!(HttpOptions {
+            proxy: Some("http://proxy.example.com:3128".to_string()),
+            connect_timeout: Some(5),
+            timeout: Some(60),
+            insecure: true,
+            ..Default::default()
+        }
+        .build_client()
+        .is_ok());
+        assert!(HttpOptions {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        }
+        .build_client()
+        .is_err());
+        assert!(HttpOptions {
+            ca_cert: Some(PathBuf::from("/nonexistent/ca.pem")),
+            ..Default::default()
+        }
+        .build_client()
+        .is_err());
+    }
+
+    #[test]
+    fn test_send_retries_server_errors() {
+        let http = HttpOptions::default();
+        let client = http.build_client().unwrap();
+
+        let url = serve(vec![503, 502, 200]);
+        let response = http.send(client.get(&url)).unwrap();
+        assert_eq!(response.status(), 200);
+
+        // Client errors are not retried.
+        let url = serve(vec![404, 200]);
+        let response = http.send(client.get(&url)).unwrap();
+        assert_eq!(response.status(), 404);
+
+        let http = HttpOptions {
+            retries: Some(1),
+            ..Default::default()
+        };
+        let url = serve(vec![500, 500, 200]);
+        let response = http.send(client.get(&url)).unwrap();
+        assert_eq!(response.status(), 500);
+    }
+
+    #[test]
+    fn test_cache_validators() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        fs::create_dir_all(&root).unwrap();
+        let cache_path = root.join("index.yaml");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        headers.insert(
+            LAST_MODIFIED,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        let validators = CacheValidators::from_headers(&headers);
+        validators.save(&cache_path).unwrap();
+        assert!(root.join("index.yaml.meta.yaml").exists());
+
+        // Validators are not used without the cached file.
+        assert!(CacheValidators::load(&cache_path).is_empty());
+
+        fs::write(&cache_path, "version: 1").unwrap();
+        assert_eq!(CacheValidators::load(&cache_path), validators);
+
+        let request = validators
+            .apply(reqwest::blocking::Client::new().get("http://localhost/index.yaml"))
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[IF_NONE_MATCH], "\"abc\"");
+        assert_eq!(
+            request.headers()[IF_MODIFIED_SINCE],
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+
+        CacheValidators::default().save(&cache_path).unwrap();
+        assert!(!root.join("index.yaml.meta.yaml").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
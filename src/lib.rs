@@ -1,9 +1,20 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 // SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
 
+pub mod archive;
+pub mod bundle;
 pub mod cli;
+pub mod config;
+pub mod flowbits;
+pub mod http;
+pub mod local;
+pub mod matchers;
+pub mod parser;
 pub mod paths;
+pub mod reload;
+pub mod report;
 pub mod rulesets;
+pub mod signature;
 pub mod sources;
 pub mod update;
 pub mod user_agent;
@@ -0,0 +1,221 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Local rule files that are merged with the rules from the sources.
+
+use crate::paths::PathProvider;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The list of local rule paths, stored next to the sources directory.
+const LOCAL_FILE: &str = "local.yaml";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LocalConfig {
+    #[serde(default)]
+    local: Vec<PathBuf>,
+}
+
+pub struct LocalRulesManager<'a> {
+    path_provider: &'a dyn PathProvider,
+}
+
+impl<'a> LocalRulesManager<'a> {
+    pub fn new(path_provider: &'a dyn PathProvider) -> Self {
+        Self { path_provider }
+    }
+
+    fn get_config_path(&self) -> PathBuf {
+        self.path_provider.sources_dir().with_file_name(LOCAL_FILE)
+    }
+
+    fn load(&self) -> Result<LocalConfig> {
+        let path = self.get_config_path();
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(LocalConfig::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn save(&self, config: &LocalConfig) -> Result<()> {
+        let path = self.get_config_path();
+        if let Some(parent) = path.parent() {
+            crate::paths::ensure_dir_exists(parent)?;
+        }
+        let yaml = serde_yaml::to_string(config)?;
+        fs::write(&path, yaml).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The configured local rule files and directories.
+    pub fn get_local_paths(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.load()?.local)
+    }
+
+    pub fn add_local(&self, path: &Path) -> Result<()> {
+        let path = fs::canonicalize(path)
+            .with_context(|| format!("Local rule path {} not found", path.display()))?;
+
+        let mut config = self.load()?;
+        if config.local.contains(&path) {
+            println!(
+                "{}: Local rules {} are already configured",
+                "Info".yellow(),
+                path.display()
+            );
+            return Ok(());
+        }
+
+        config.local.push(path.clone());
+        self.save(&config)?;
+        println!(
+            "{}: Added local rules {}",
+            "Success".green(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn remove_local(&self, path: &Path) -> Result<()> {
+        // The path may no longer exist, in which case match it as given.
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+        let mut config = self.load()?;
+        let count = config.local.len();
+        config
+            .local
+            .retain(|local| local != path && local != &canonical);
+        if config.local.len() == count {
+            return Err(anyhow::anyhow!(
+                "Local rules {} are not configured",
+                path.display()
+            ));
+        }
+
+        self.save(&config)?;
+        println!(
+            "{}: Removed local rules {}",
+            "Success".green(),
+            path.display()
+        );
+        Ok(())
+    }
+}
+
+/// Expand the local rule paths into rule files. Directories contribute
+/// their `*.rules` files, sorted by name so the result is deterministic.
+pub fn collect_rule_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut dir_files = Vec::new();
+            for entry in fs::read_dir(path)
+                .with_context(|| format!("Failed to read directory {}", path.display()))?
+            {
+                let entry_path = entry?.path();
+                if entry_path.is_file()
+                    && entry_path
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("rules"))
+                {
+                    dir_files.push(entry_path);
+                }
+            }
+            dir_files.sort();
+            files.extend(dir_files);
+        } else if path.is_file() {
+            files.push(path.clone());
+        } else {
+            return Err(anyhow::anyhow!(
+                "Local rule path {} not found",
+                path.display()
+            ));
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct TestPaths {
+        root: PathBuf,
+    }
+
+    impl PathProvider for TestPaths {
+        fn sources_dir(&self) -> PathBuf {
+            self.root.join("update").join("sources")
+        }
+
+        fn cache_dir(&self) -> PathBuf {
+            self.root.join("cache")
+        }
+
+        fn rules_dir(&self) -> PathBuf {
+            self.root.join("rules")
+        }
+
+        fn config_dir(&self) -> PathBuf {
+            self.root.join("config")
+        }
+    }
+
+    fn test_root(name: &str) -> PathBuf {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-{name}-{unique}"));
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_add_and_remove_local() {
+        let root = test_root("local");
+        let rules = root.join("local.rules");
+        fs::write(&rules, "").unwrap();
+        let paths = TestPaths { root: root.clone() };
+        let manager = LocalRulesManager::new(&paths);
+
+        manager.add_local(&rules).unwrap();
+        manager.add_local(&rules).unwrap();
+        let canonical = fs::canonicalize(&rules).unwrap();
+        assert_eq!(manager.get_local_paths().unwrap(), vec![canonical]);
+        assert!(root.join("update").join(LOCAL_FILE).exists());
+
+        manager.remove_local(&rules).unwrap();
+        assert!(manager.get_local_paths().unwrap().is_empty());
+        assert!(manager.remove_local(&rules).is_err());
+        assert!(manager.add_local(&root.join("missing.rules")).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_collect_rule_files() {
+        let root = test_root("local-files");
+        let dir = root.join("rules");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.rules"), "").unwrap();
+        fs::write(dir.join("a.rules"), "").unwrap();
+        fs::write(dir.join("list.txt"), "").unwrap();
+        let single = root.join("single.rules");
+        fs::write(&single, "").unwrap();
+
+        let files = collect_rule_files(&[single.clone(), dir.clone()]).unwrap();
+        assert_eq!(
+            files,
+            vec![single, dir.join("a.rules"), dir.join("b.rules")]
+        );
+        assert!(collect_rule_files(&[root.join("missing")]).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
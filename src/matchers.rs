@@ -0,0 +1,453 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Suricata-Update compatible rule matching for enable.conf, disable.conf,
+//! drop.conf and modify.conf.
+
+use crate::update::Rule;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const ENABLE_CONF: &str = "enable.conf";
+pub const DISABLE_CONF: &str = "disable.conf";
+pub const DROP_CONF: &str = "drop.conf";
+pub const MODIFY_CONF: &str = "modify.conf";
+
+#[derive(Debug)]
+pub enum RuleMatcher {
+    /// One or more gid:sid pairs, a bare sid implies a gid of 1.
+    Id(Vec<(u32, u32)>),
+    /// "re:" - a regular expression matched case-insensitively against the
+    /// full rule text.
+    Regex(Regex),
+    /// "group:" - a filename pattern matched against the rule file name.
+    Group(Regex),
+    /// "metadata:" - a metadata key and value, compared case-insensitively.
+    Metadata { key: String, value: String },
+}
+
+impl RuleMatcher {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+
+        if let Some(pattern) = spec.strip_prefix("re:") {
+            let regex = RegexBuilder::new(pattern.trim())
+                .case_insensitive(true)
+                .build()
+                .with_context(|| format!("Invalid regular expression: {}", pattern.trim()))?;
+            return Ok(Self::Regex(regex));
+        }
+
+        if let Some(pattern) = spec.strip_prefix("group:") {
+            return Ok(Self::Group(glob_to_regex(pattern.trim())?));
+        }
+
+        if let Some(metadata) = spec.strip_prefix("metadata:") {
+            let (key, value) =
+                metadata
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Invalid metadata matcher, expected \"metadata:<key> <value>\""
+                        )
+                    })?;
+            return Ok(Self::Metadata {
+                key: key.trim().to_ascii_lowercase(),
+                value: value.trim().to_ascii_lowercase(),
+            });
+        }
+
+        let mut ids = Vec::new();
+        for id in spec.split(',') {
+            let id = id.trim();
+            let parsed = match id.split_once(':') {
+                Some((gid, sid)) => gid
+                    .trim()
+                    .parse::<u32>()
+                    .ok()
+                    .zip(sid.trim().parse::<u32>().ok()),
+                None => id.parse::<u32>().ok().map(|sid| (1, sid)),
+            };
+            ids.push(parsed.ok_or_else(|| anyhow::anyhow!("Invalid rule matcher: {}", spec))?);
+        }
+        Ok(Self::Id(ids))
+    }
+
+    pub(crate) fn matches(&self, rule: &Rule) -> bool {
+        match self {
+            Self::Id(ids) => ids
+                .iter()
+                .any(|(gid, sid)| *gid == rule.gid && *sid == rule.sid),
+            Self::Regex(regex) => regex.is_match(&rule.raw),
+            Self::Group(pattern) => {
+                let filename = Path::new(&rule.group)
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default();
+                pattern.is_match(&filename)
+            }
+            Self::Metadata { key, value } => rule
+                .option_values("metadata")
+                .flat_map(|metadata| metadata.split(','))
+                .filter_map(|entry| entry.trim().split_once(char::is_whitespace))
+                .any(|(k, v)| k.eq_ignore_ascii_case(key) && v.trim().eq_ignore_ascii_case(value)),
+        }
+    }
+}
+
+/// A modify.conf entry: rules matching the matcher have the regular
+/// expression replaced.
+#[derive(Debug)]
+pub struct ModifyFilter {
+    matcher: RuleMatcher,
+    pattern: Regex,
+    replacement: String,
+}
+
+impl ModifyFilter {
+    pub fn parse(line: &str) -> Result<Self> {
+        static MODIFY_RE: OnceLock<Regex> = OnceLock::new();
+        let modify_re = MODIFY_RE
+            .get_or_init(|| Regex::new(r#"^(.+?)\s+"(.*)"\s+"(.*)"$"#).expect("valid regex"));
+
+        let captures = modify_re.captures(line.trim()).ok_or_else(|| {
+            anyhow::anyhow!("Invalid modify filter, expected <matcher> \"<from>\" \"<to>\"")
+        })?;
+        let pattern = Regex::new(&captures[2])
+            .with_context(|| format!("Invalid regular expression: {}", &captures[2]))?;
+
+        Ok(Self {
+            matcher: RuleMatcher::parse(&captures[1])?,
+            pattern,
+            replacement: convert_replacement(&captures[3]),
+        })
+    }
+
+    /// Returns the modified rule text, if the rule matched and was changed.
+    pub(crate) fn apply(&self, rule: &Rule) -> Option<String> {
+        if !self.matcher.matches(rule) {
+            return None;
+        }
+        let modified = self
+            .pattern
+            .replace_all(&rule.raw, self.replacement.as_str())
+            .into_owned();
+        (modified != rule.raw).then_some(modified)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RuleConfStats {
+    pub enabled: usize,
+    pub disabled: usize,
+    pub dropped: usize,
+    pub modified: usize,
+}
+
+impl RuleConfStats {
+    pub fn is_empty(&self) -> bool {
+        self.enabled == 0 && self.disabled == 0 && self.dropped == 0 && self.modified == 0
+    }
+}
+
+/// The rule matchers and filters loaded from enable.conf, disable.conf,
+/// drop.conf and modify.conf, and the patterns of rule files to ignore.
+#[derive(Debug, Default)]
+pub struct RuleConf {
+    enable: Vec<RuleMatcher>,
+    disable: Vec<RuleMatcher>,
+    drop: Vec<RuleMatcher>,
+    modify: Vec<ModifyFilter>,
+    ignore: Vec<Regex>,
+}
+
+impl RuleConf {
+    /// Load any of the configuration files that exist in the given directory.
+    pub fn load_dir(dir: &Path) -> Result<Self> {
+        let existing = |name: &str| -> Option<PathBuf> {
+            let path = dir.join(name);
+            path.exists().then_some(path)
+        };
+        Self::load(
+            existing(ENABLE_CONF).as_deref(),
+            existing(DISABLE_CONF).as_deref(),
+            existing(DROP_CONF).as_deref(),
+            existing(MODIFY_CONF).as_deref(),
+        )
+    }
+
+    pub fn load(
+        enable: Option<&Path>,
+        disable: Option<&Path>,
+        drop: Option<&Path>,
+        modify: Option<&Path>,
+    ) -> Result<Self> {
+        let mut conf = Self::default();
+        if let Some(path) = enable {
+            conf.enable = Self::load_matchers(path)?;
+        }
+        if let Some(path) = disable {
+            conf.disable = Self::load_matchers(path)?;
+        }
+        if let Some(path) = drop {
+            conf.drop = Self::load_matchers(path)?;
+        }
+        if let Some(path) = modify {
+            conf.modify = Self::load_lines(path, ModifyFilter::parse)?;
+        }
+        Ok(conf)
+    }
+
+    /// Ignore rule files with a filename matching any of the patterns.
+    pub fn with_ignore(mut self, patterns: &[String]) -> Result<Self> {
+        self.ignore = patterns
+            .iter()
+            .map(|pattern| glob_to_regex(pattern.trim()))
+            .collect::<Result<_>>()?;
+        Ok(self)
+    }
+
+    /// Returns true if the rule file should be ignored. Patterns are
+    /// matched against the filename without its directory.
+    pub fn is_ignored(&self, filename: &str) -> bool {
+        let filename = Path::new(filename)
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        self.ignore
+            .iter()
+            .any(|pattern| pattern.is_match(&filename))
+    }
+
+    fn load_matchers(path: &Path) -> Result<Vec<RuleMatcher>> {
+        Self::load_lines(path, RuleMatcher::parse)
+    }
+
+    fn load_lines<T>(path: &Path, parse: impl Fn(&str) -> Result<T>) -> Result<Vec<T>> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut entries = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry =
+                parse(line).with_context(|| format!("{}:{}: {}", path.display(), i + 1, line))?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.enable.is_empty()
+            && self.disable.is_empty()
+            && self.drop.is_empty()
+            && self.modify.is_empty()
+    }
+
+    /// Apply the configuration to a rule, in the same order as
+    /// Suricata-Update: disable, enable, drop then modify.
+    ///
+    /// Returns true if the rule was matched by disable.conf and remains
+    /// disabled, such rules must not be enabled again to resolve flowbits.
+    pub(crate) fn apply(&self, rule: &mut Rule, stats: &mut RuleConfStats) -> bool {
+        let disable_matched = self.disable.iter().any(|m| m.matches(rule));
+        if rule.enabled && disable_matched {
+            rule.enabled = false;
+            stats.disabled += 1;
+        }
+
+        if !rule.enabled && self.enable.iter().any(|m| m.matches(rule)) {
+            rule.enabled = true;
+            stats.enabled += 1;
+        }
+
+        if self.drop.iter().any(|m| m.matches(rule)) && rule.set_action("drop") {
+            stats.dropped += 1;
+        }
+
+        let mut modified = false;
+        for filter in &self.modify {
+            if let Some(raw) = filter.apply(rule) {
+                match rule.set_raw(raw) {
+                    Ok(()) => modified = true,
+                    Err(err) => eprintln!(
+                        "{}: modify.conf produced an invalid rule for {}:{}: {}",
+                        "Warning".yellow(),
+                        rule.gid,
+                        rule.sid,
+                        err
+                    ),
+                }
+            }
+        }
+        if modified {
+            stats.modified += 1;
+        }
+
+        disable_matched && !rule.enabled
+    }
+}
+
+/// Convert a filename glob pattern ("*", "?") into an anchored regular
+/// expression.
+pub fn glob_to_regex(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).with_context(|| format!("Invalid pattern: {pattern}"))
+}
+
+/// Convert Python style back-references (\1) used by Suricata-Update's
+/// modify.conf into the form used by the regex crate (${1}).
+fn convert_replacement(replacement: &str) -> String {
+    let mut converted = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => converted.push_str("$$"),
+            '\\' if chars.peek().is_some_and(|next| next.is_ascii_digit()) => {
+                let mut group = String::new();
+                while let Some(digit) = chars.peek().filter(|next| next.is_ascii_digit()) {
+                    group.push(*digit);
+                    chars.next();
+                }
+                converted.push_str(&format!("${{{group}}}"));
+            }
+            c => converted.push(c),
+        }
+    }
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(raw: &str, enabled: bool) -> Rule {
+        Rule::parse(raw, enabled, "rules/emerging-scan.rules").unwrap()
+    }
+
+    #[test]
+    fn test_id_matcher() {
+        let r = rule("alert ip any any -> any any (sid:100;)", true);
+        assert!(RuleMatcher::parse("100").unwrap().matches(&r));
+        assert!(RuleMatcher::parse("1:100").unwrap().matches(&r));
+        assert!(RuleMatcher::parse("1:99, 1:100").unwrap().matches(&r));
+        assert!(!RuleMatcher::parse("3:100").unwrap().matches(&r));
+        assert!(RuleMatcher::parse("abc").is_err());
+    }
+
+    #[test]
+    fn test_group_and_regex_matchers() {
+        let r = rule(
+            "alert ip any any -> any any (msg:\"ET SCAN test\"; sid:100;)",
+            false,
+        );
+        assert!(RuleMatcher::parse("group:emerging-scan.rules")
+            .unwrap()
+            .matches(&r));
+        assert!(RuleMatcher::parse("group:emerging-*").unwrap().matches(&r));
+        assert!(!RuleMatcher::parse("group:rules/emerging-*")
+            .unwrap()
+            .matches(&r));
+        assert!(RuleMatcher::parse("re:^alert ip").unwrap().matches(&r));
+        assert!(!RuleMatcher::parse("re:^drop").unwrap().matches(&r));
+        assert!(RuleMatcher::parse("re:et scan").unwrap().matches(&r));
+    }
+
+    #[test]
+    fn test_metadata_matcher() {
+        let r = rule(
+            "alert ip any any -> any any (msg:\"test\"; metadata: policy balanced-ips, Deployment Perimeter; sid:100;)",
+            true,
+        );
+        assert!(RuleMatcher::parse("metadata: deployment perimeter")
+            .unwrap()
+            .matches(&r));
+        assert!(RuleMatcher::parse("metadata:policy balanced-ips")
+            .unwrap()
+            .matches(&r));
+        assert!(!RuleMatcher::parse("metadata:policy security-ips")
+            .unwrap()
+            .matches(&r));
+    }
+
+    #[test]
+    fn test_modify_filter() {
+        let filter = ModifyFilter::parse(r#"1:100 "^alert ip (\S+)" "alert tcp \1""#).unwrap();
+        let r = rule("alert ip any any -> any any (msg:\"test\"; sid:100;)", true);
+        assert_eq!(
+            filter.apply(&r).unwrap(),
+            "alert tcp any any -> any any (msg:\"test\"; sid:100;)"
+        );
+
+        let other = rule("alert ip any any -> any any (msg:\"test\"; sid:200;)", true);
+        assert!(filter.apply(&other).is_none());
+    }
+
+    #[test]
+    fn test_rule_conf_apply() {
+        let conf = RuleConf {
+            enable: vec![RuleMatcher::parse("200").unwrap()],
+            disable: vec![RuleMatcher::parse("re:disable me").unwrap()],
+            drop: vec![RuleMatcher::parse("200").unwrap()],
+            modify: Vec::new(),
+            ignore: Vec::new(),
+        };
+        let mut stats = RuleConfStats::default();
+
+        let mut disabled = rule(
+            "alert ip any any -> any any (msg:\"disable me\"; sid:100;)",
+            true,
+        );
+        assert!(conf.apply(&mut disabled, &mut stats));
+        assert!(!disabled.enabled);
+
+        let mut enabled = rule(
+            "alert ip any any -> any any (msg:\"enable me\"; sid:200;)",
+            false,
+        );
+        assert!(!conf.apply(&mut enabled, &mut stats));
+        assert!(enabled.enabled);
+        assert_eq!(
+            enabled.raw,
+            "drop ip any any -> any any (msg:\"enable me\"; sid:200;)"
+        );
+
+        assert_eq!(stats.disabled, 1);
+        assert_eq!(stats.enabled, 1);
+        assert_eq!(stats.dropped, 1);
+        assert_eq!(stats.modified, 0);
+    }
+
+    #[test]
+    fn test_rule_conf_ignore() {
+        let conf = RuleConf::default()
+            .with_ignore(&["*deleted.rules".to_string()])
+            .unwrap();
+        assert!(conf.is_ignored("rules/deleted.rules"));
+        assert!(conf.is_ignored("emerging-deleted.rules"));
+        assert!(!conf.is_ignored("rules/emerging-scan.rules"));
+        assert!(!RuleConf::default().is_ignored("deleted.rules"));
+    }
+
+    #[test]
+    fn test_convert_replacement() {
+        assert_eq!(convert_replacement(r"\1 and \12"), "${1} and ${12}");
+        assert_eq!(convert_replacement("$HOME_NET"), "$$HOME_NET");
+        assert_eq!(convert_replacement(r"a\b"), r"a\b");
+    }
+}
@@ -0,0 +1,344 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Suricata rule parser.
+//!
+//! Splits a rule into its header (action, protocol, addresses, ports and
+//! direction) and an ordered list of options, honoring quoting and
+//! backslash escapes in option values.
+
+use std::fmt;
+
+pub const ACTIONS: &[&str] = &[
+    "alert",
+    "pass",
+    "drop",
+    "reject",
+    "rejectsrc",
+    "rejectdst",
+    "rejectboth",
+    "config",
+];
+
+const DIRECTIONS: &[&str] = &["->", "<>", "=>"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleOption {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedRule {
+    pub action: String,
+    pub protocol: String,
+    pub src_addr: String,
+    pub src_port: String,
+    pub direction: String,
+    pub dst_addr: String,
+    pub dst_port: String,
+    pub options: Vec<RuleOption>,
+}
+
+impl ParsedRule {
+    /// The value of the first option with the given name.
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|option| option.name == name)
+            .and_then(|option| option.value.as_deref())
+    }
+
+    fn numeric_option(&self, name: &str) -> Result<Option<u32>, ParseError> {
+        match self.option(name) {
+            Some(value) => value
+                .trim()
+                .parse::<u32>()
+                .map(Some)
+                .map_err(|_| ParseError::new(format!("invalid {name}: {value}"))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn sid(&self) -> Result<Option<u32>, ParseError> {
+        self.numeric_option("sid")
+    }
+
+    pub fn gid(&self) -> Result<Option<u32>, ParseError> {
+        self.numeric_option("gid")
+    }
+
+    pub fn rev(&self) -> Result<Option<u32>, ParseError> {
+        self.numeric_option("rev")
+    }
+
+    pub fn msg(&self) -> Option<String> {
+        self.option("msg").map(unquote)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    message: String,
+}
+
+impl ParseError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Returns true if the text starts with a known rule action. Used to tell
+/// commented out rules apart from ordinary comments.
+pub fn starts_with_action(text: &str) -> bool {
+    text.split_whitespace()
+        .next()
+        .is_some_and(|word| ACTIONS.contains(&word))
+}
+
+pub fn parse_rule(text: &str) -> Result<ParsedRule, ParseError> {
+    let text = text.trim();
+
+    let open = text
+        .find('(')
+        .ok_or_else(|| ParseError::new("missing option list"))?;
+    if !text.ends_with(')') {
+        return Err(ParseError::new("rule does not end with ')'"));
+    }
+
+    let header = split_header(&text[..open])?;
+    let [action, protocol, src_addr, src_port, direction, dst_addr, dst_port] = header;
+
+    if !ACTIONS.contains(&action.as_str()) {
+        return Err(ParseError::new(format!("unknown action: {action}")));
+    }
+    if !DIRECTIONS.contains(&direction.as_str()) {
+        return Err(ParseError::new(format!("invalid direction: {direction}")));
+    }
+
+    let options = parse_options(&text[open + 1..text.len() - 1])?;
+
+    Ok(ParsedRule {
+        action,
+        protocol,
+        src_addr,
+        src_port,
+        direction,
+        dst_addr,
+        dst_port,
+        options,
+    })
+}
+
+/// Split the rule header into its 7 fields. Address and port lists may
+/// contain whitespace inside brackets.
+fn split_header(header: &str) -> Result<[String; 7], ParseError> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+
+    for c in header.chars() {
+        match c {
+            '[' => {
+                depth += 1;
+                current.push(c);
+            }
+            ']' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| ParseError::new("unbalanced ']' in rule header"))?;
+                current.push(c);
+            }
+            c if c.is_whitespace() && depth == 0 => {
+                if !current.is_empty() {
+                    fields.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if depth != 0 {
+        return Err(ParseError::new("unbalanced '[' in rule header"));
+    }
+    if !current.is_empty() {
+        fields.push(current);
+    }
+
+    let count = fields.len();
+    fields.try_into().map_err(|_| {
+        ParseError::new(format!(
+            "invalid rule header, expected 7 fields, found {count}"
+        ))
+    })
+}
+
+fn parse_options(body: &str) -> Result<Vec<RuleOption>, ParseError> {
+    let mut options = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = body.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                match chars.next() {
+                    Some(next) => current.push(next),
+                    None => return Err(ParseError::new("trailing escape character")),
+                }
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            ';' if !in_quotes => {
+                options.push(parse_option(&current)?);
+                current.clear();
+            }
+            c => current.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(ParseError::new("unterminated quoted string"));
+    }
+    if !current.trim().is_empty() {
+        return Err(ParseError::new(format!(
+            "option not terminated by ';': {}",
+            current.trim()
+        )));
+    }
+
+    Ok(options)
+}
+
+fn parse_option(option: &str) -> Result<RuleOption, ParseError> {
+    let option = option.trim();
+    let (name, value) = match option.split_once(':') {
+        Some((name, value)) => (name.trim(), Some(value.trim().to_string())),
+        None => (option, None),
+    };
+
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(ParseError::new(format!("invalid option: {option}")));
+    }
+
+    Ok(RuleOption {
+        name: name.to_string(),
+        value,
+    })
+}
+
+/// Remove surrounding double quotes and backslash escapes from an option
+/// value.
+pub fn unquote(value: &str) -> String {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+
+    let mut unquoted = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                unquoted.push(next);
+            }
+        } else {
+            unquoted.push(c);
+        }
+    }
+    unquoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule() {
+        let rule = parse_rule(
+            r#"alert tcp $HOME_NET any -> [10.0.0.0/8, !10.1.0.0/16] [80,443] (msg:"ET TEST; with \"quotes\""; content:"sid:9999\;"; flow:established,to_server; sid:2000001; rev:3;)"#,
+        )
+        .unwrap();
+
+        assert_eq!(rule.action, "alert");
+        assert_eq!(rule.protocol, "tcp");
+        assert_eq!(rule.src_addr, "$HOME_NET");
+        assert_eq!(rule.src_port, "any");
+        assert_eq!(rule.direction, "->");
+        assert_eq!(rule.dst_addr, "[10.0.0.0/8, !10.1.0.0/16]");
+        assert_eq!(rule.dst_port, "[80,443]");
+        assert_eq!(
+            rule.options
+                .iter()
+                .map(|option| option.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["msg", "content", "flow", "sid", "rev"]
+        );
+        assert_eq!(rule.option("content"), Some(r#""sid:9999\;""#));
+        assert_eq!(rule.msg().unwrap(), r#"ET TEST; with "quotes""#);
+        assert_eq!(rule.sid().unwrap(), Some(2000001));
+        assert_eq!(rule.gid().unwrap(), None);
+        assert_eq!(rule.rev().unwrap(), Some(3));
+    }
+
+    #[test]
+    fn test_parse_rule_actions() {
+        for action in ACTIONS {
+            let rule = parse_rule(&format!(
+                "{action} ip any any <> any any (msg:\"test\"; sid:1;)"
+            ))
+            .unwrap();
+            assert_eq!(rule.action, *action);
+            assert_eq!(rule.direction, "<>");
+        }
+        assert!(parse_rule("log ip any any -> any any (sid:1;)").is_err());
+    }
+
+    #[test]
+    fn test_parse_rule_flag_options() {
+        let rule =
+            parse_rule("alert http any any => any any (msg:\"test\"; nocase; sid:1;)").unwrap();
+        assert_eq!(rule.direction, "=>");
+        assert_eq!(
+            rule.options[1],
+            RuleOption {
+                name: "nocase".to_string(),
+                value: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rule_errors() {
+        assert!(parse_rule("alert ip any any -> any any").is_err());
+        assert!(parse_rule("alert ip any any any (sid:1;)").is_err());
+        assert!(parse_rule("alert ip any any -> any any (msg:\"test; sid:1;)").is_err());
+        assert!(parse_rule("alert ip any any -> any any (msg:\"test\"; sid:1)").is_err());
+        assert!(parse_rule("alert ip any any -> any any (sid:abc;)")
+            .unwrap()
+            .sid()
+            .is_err());
+    }
+
+    #[test]
+    fn test_starts_with_action() {
+        assert!(starts_with_action(
+            "rejectboth tcp any any -> any any (sid:1;)"
+        ));
+        assert!(!starts_with_action("This is a comment"));
+        assert!(!starts_with_action(""));
+    }
+}
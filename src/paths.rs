@@ -4,10 +4,12 @@
 use directories::BaseDirs;
 use std::path::{Path, PathBuf};
 
-pub trait PathProvider {
+// Send + Sync so the path provider can be shared with download workers.
+pub trait PathProvider: Send + Sync {
     fn sources_dir(&self) -> PathBuf;
     fn cache_dir(&self) -> PathBuf;
     fn rules_dir(&self) -> PathBuf;
+    fn config_dir(&self) -> PathBuf;
 }
 
 pub struct UnixSystemPaths;
@@ -24,6 +26,10 @@ impl PathProvider for UnixSystemPaths {
     fn rules_dir(&self) -> PathBuf {
         PathBuf::from("/var/lib/suricata/rules")
     }
+
+    fn config_dir(&self) -> PathBuf {
+        PathBuf::from("/etc/suricata")
+    }
 }
 
 pub struct UserPaths {
@@ -58,6 +64,11 @@ impl PathProvider for UserPaths {
             .join("suricata")
             .join("rules")
     }
+
+    fn config_dir(&self) -> PathBuf {
+        // Use ~/.config/suricata for enable.conf, disable.conf, etc.
+        self.base_dirs.config_dir().join("suricata")
+    }
 }
 
 pub fn get_path_provider(user_mode: bool) -> Box<dyn PathProvider> {
@@ -89,6 +100,43 @@ pub fn ensure_dir_exists(path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Set the modification time of a file to now, marking a cached file as
+/// fresh.
+pub fn touch(path: &Path) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let file = std::fs::File::options()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.set_modified(std::time::SystemTime::now())
+        .with_context(|| format!("Failed to update timestamp of {}", path.display()))?;
+    Ok(())
+}
+
+/// The local path of a source or index URL: the path of a file:// URL, or
+/// the URL itself if it has no scheme. None for remote URLs. Plain paths are
+/// only accepted from the local configuration, see [`has_scheme`].
+pub fn local_url_path(url: &str) -> anyhow::Result<Option<PathBuf>> {
+    if url.starts_with("file:") {
+        let path = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid file URL: {url}"))?;
+        Ok(Some(path))
+    } else if url.contains("://") {
+        Ok(None)
+    } else {
+        Ok(Some(PathBuf::from(url)))
+    }
+}
+
+/// Whether a URL has a scheme rather than being a plain path. A URL from an
+/// index must have one, so an index can't point a source at any local file.
+pub fn has_scheme(url: &str) -> bool {
+    url.starts_with("file:") || url.contains("://")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,6 +153,28 @@ mod tests {
             PathBuf::from("/var/lib/suricata/update/cache")
         );
         assert_eq!(paths.rules_dir(), PathBuf::from("/var/lib/suricata/rules"));
+        assert_eq!(paths.config_dir(), PathBuf::from("/etc/suricata"));
+    }
+
+    #[test]
+    fn test_local_url_path() {
+        assert_eq!(
+            local_url_path("file:///srv/rules/index.yaml").unwrap(),
+            Some(PathBuf::from("/srv/rules/index.yaml"))
+        );
+        assert_eq!(
+            local_url_path("file:///srv/vetted%20rules").unwrap(),
+            Some(PathBuf::from("/srv/vetted rules"))
+        );
+        assert_eq!(
+            local_url_path("/srv/rules").unwrap(),
+            Some(PathBuf::from("/srv/rules"))
+        );
+        assert_eq!(
+            local_url_path("https://example.com/rules.tar.gz").unwrap(),
+            None
+        );
+        assert!(local_url_path("file://remote-host/rules").is_err());
     }
 
     #[test]
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Reloading the rules of a running Suricata.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+pub const DEFAULT_COMMAND_SOCKET: &str = "/var/run/suricata/suricata-command.socket";
+
+/// Run a user provided reload command through the shell.
+pub fn run_reload_command(command: &str) -> Result<()> {
+    let status = if cfg!(target_os = "windows") {
+        std::process::Command::new("cmd")
+            .args(["/C", command])
+            .status()
+    } else {
+        std::process::Command::new("sh")
+            .args(["-c", command])
+            .status()
+    }
+    .with_context(|| format!("Failed to run reload command: {command}"))?;
+
+    if !status.success() {
+        return Err(anyhow::anyhow!(
+            "Reload command failed ({}): {}",
+            status,
+            command
+        ));
+    }
+    Ok(())
+}
+
+/// Send the reload-rules command to Suricata over its unix command socket,
+/// returning the message from Suricata.
+#[cfg(unix)]
+pub fn reload_rules_socket(socket_path: &Path) -> Result<String> {
+    use serde_json::{json, Value};
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    fn read_response(stream: &UnixStream) -> Result<Value> {
+        serde_json::Deserializer::from_reader(stream)
+            .into_iter::<Value>()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Connection closed by Suricata"))?
+            .context("Invalid response from Suricata")
+    }
+
+    fn check_response(response: &Value) -> Result<String> {
+        let message = match response.get("message") {
+            Some(Value::String(message)) => message.clone(),
+            Some(message) => message.to_string(),
+            None => String::new(),
+        };
+        match response.get("return").and_then(Value::as_str) {
+            Some("OK") => Ok(message),
+            _ => Err(anyhow::anyhow!("Suricata returned an error: {}", message)),
+        }
+    }
+
+    let mut stream = UnixStream::connect(socket_path).with_context(|| {
+        format!(
+            "Failed to connect to Suricata command socket {}",
+            socket_path.display()
+        )
+    })?;
+    stream.set_read_timeout(Some(Duration::from_secs(600)))?;
+
+    stream.write_all(json!({"version": "0.2"}).to_string().as_bytes())?;
+    check_response(&read_response(&stream)?).context("Suricata rejected the protocol version")?;
+
+    stream.write_all(json!({"command": "reload-rules"}).to_string().as_bytes())?;
+    check_response(&read_response(&stream)?)
+}
+
+#[cfg(not(unix))]
+pub fn reload_rules_socket(socket_path: &Path) -> Result<String> {
+    Err(anyhow::anyhow!(
+        "Reloading over the command socket {} is not supported on this platform, use --reload-command",
+        socket_path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reload_rules_socket() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixListener;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let socket_path = std::env::temp_dir().join(format!("suricasta-rules-{unique}.socket"));
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        // Emulate Suricata, which does not delimit commands with newlines, so
+        // the test client sends each command on its own read.
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut commands = Vec::new();
+            for _ in 0..2 {
+                let buf = reader.fill_buf().unwrap().to_vec();
+                reader.consume(buf.len());
+                commands.push(String::from_utf8(buf).unwrap());
+                let response = if commands.len() == 1 {
+                    r#"{"return": "OK"}"#
+                } else {
+                    r#"{"return": "OK", "message": "done"}"#
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+                stream.write_all(b"\n").unwrap();
+            }
+            commands
+        });
+
+        let message = reload_rules_socket(&socket_path).unwrap();
+        let commands = server.join().unwrap();
+        std::fs::remove_file(&socket_path).unwrap();
+
+        assert_eq!(message, "done");
+        assert!(commands[0].contains("\"version\""));
+        assert!(commands[1].contains("reload-rules"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_reload_command() {
+        assert!(run_reload_command("true").is_ok());
+        assert!(run_reload_command("exit 3").is_err());
+    }
+}
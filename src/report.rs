@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Report of the rule changes made by an update.
+
+use crate::update::Rule;
+use anyhow::{Context, Result};
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Number of rules listed per change type on the console.
+const MAX_LISTED: usize = 20;
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct RuleChange {
+    pub gid: u32,
+    pub sid: u32,
+    pub rev: u32,
+    #[serde(rename = "previous-rev", skip_serializing_if = "Option::is_none")]
+    pub previous_rev: Option<u32>,
+    pub msg: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RuleReport {
+    pub timestamp: String,
+    pub added: Vec<RuleChange>,
+    pub removed: Vec<RuleChange>,
+    pub modified: Vec<RuleChange>,
+    pub enabled: Vec<RuleChange>,
+    pub disabled: Vec<RuleChange>,
+}
+
+impl RuleReport {
+    /// Compare the rules from the previous output with the new rules, both
+    /// keyed by gid:sid.
+    pub(crate) fn new(old: &HashMap<String, Rule>, new: &HashMap<String, Rule>) -> Self {
+        let mut report = Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            ..Default::default()
+        };
+
+        for (key, rule) in new {
+            match old.get(key) {
+                None if rule.enabled => report.added.push(Self::change(rule, None)),
+                None => {}
+                Some(old_rule) => {
+                    if old_rule.enabled && !rule.enabled {
+                        report.disabled.push(Self::change(rule, None));
+                    } else if !old_rule.enabled && rule.enabled {
+                        report.enabled.push(Self::change(rule, None));
+                    }
+                    if rule.enabled && rule.rev > old_rule.rev {
+                        report.modified.push(Self::change(rule, Some(old_rule.rev)));
+                    }
+                }
+            }
+        }
+
+        for (key, rule) in old {
+            if !new.contains_key(key) {
+                report.removed.push(Self::change(rule, None));
+            }
+        }
+
+        for changes in [
+            &mut report.added,
+            &mut report.removed,
+            &mut report.modified,
+            &mut report.enabled,
+            &mut report.disabled,
+        ] {
+            changes.sort_by_key(|change| (change.gid, change.sid));
+        }
+
+        report
+    }
+
+    fn change(rule: &Rule, previous_rev: Option<u32>) -> RuleChange {
+        RuleChange {
+            gid: rule.gid,
+            sid: rule.sid,
+            rev: rule.rev,
+            previous_rev,
+            msg: rule.msg.clone(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.enabled.is_empty()
+            && self.disabled.is_empty()
+    }
+
+    pub fn print(&self) {
+        println!(
+            "\nRule changes: {} added, {} removed, {} modified, {} enabled, {} disabled",
+            self.added.len().to_string().green(),
+            self.removed.len().to_string().red(),
+            self.modified.len().to_string().yellow(),
+            self.enabled.len().to_string().green(),
+            self.disabled.len().to_string().red()
+        );
+        Self::print_changes("Added", "+".green(), &self.added);
+        Self::print_changes("Removed", "-".red(), &self.removed);
+        Self::print_changes("Modified", "~".yellow(), &self.modified);
+        Self::print_changes("Enabled", "+".green(), &self.enabled);
+        Self::print_changes("Disabled", "-".red(), &self.disabled);
+    }
+
+    fn print_changes(title: &str, marker: ColoredString, changes: &[RuleChange]) {
+        if changes.is_empty() {
+            return;
+        }
+        println!("  {title}:");
+        for change in changes.iter().take(MAX_LISTED) {
+            let rev = match change.previous_rev {
+                Some(previous) => format!("rev {} -> {}", previous, change.rev),
+                None => format!("rev {}", change.rev),
+            };
+            println!(
+                "    {} {}:{} {} {}",
+                marker,
+                change.gid,
+                change.sid,
+                change.msg,
+                format!("({rev})").bright_black()
+            );
+        }
+        if changes.len() > MAX_LISTED {
+            println!(
+                "    {}",
+                format!(
+                    "... and {} more (see --report-json)",
+                    changes.len() - MAX_LISTED
+                )
+                .bright_black()
+            );
+        }
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            crate::paths::ensure_dir_exists(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+            .with_context(|| format!("Failed to write report to {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(rules: &[(&str, bool)]) -> HashMap<String, Rule> {
+        rules
+            .iter()
+            .map(|(raw, enabled)| {
+                let rule = Rule::parse(raw, *enabled, "suricata.rules").unwrap();
+                (format!("{}:{}", rule.gid, rule.sid), rule)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rule_report_no_changes() {
+        let old = rules(&[(
+            "alert ip any any -> any any (msg:\"kept\"; sid:1; rev:1;)",
+            true,
+        )]);
+        let report = RuleReport::new(&old, &old);
+        assert!(report.is_empty());
+    }
+}
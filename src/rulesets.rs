@@ -3,14 +3,22 @@
 
 use crate::paths::PathProvider;
 use crate::sources::{SourceIndex, SourceInfo};
+use crate::update::UpdateManager;
 use anyhow::{Context, Result};
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The source enabled along with the first source.
+const DEFAULT_SOURCE: &str = "et/open";
+
+/// The value parameters are replaced with where they must not be shown.
+const MASKED_PARAM: &str = "***";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnabledSource {
     pub source: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -22,6 +30,10 @@ pub struct EnabledSource {
     pub http_header: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub checksum: Option<bool>,
+    /// Minisign public key the signature of the download is verified with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "public-key")]
+    pub public_key: Option<String>,
 }
 
 impl EnabledSource {
@@ -32,6 +44,32 @@ impl EnabledSource {
             params: None,
             http_header: None,
             checksum: None,
+            public_key: None,
+        }
+    }
+
+    /// The index entry of the source, or for a custom source added by URL
+    /// that is not in the index, information made from its URL.
+    pub fn source_info(&self, index: &SourceIndex) -> Option<SourceInfo> {
+        index
+            .sources
+            .get(&self.source)
+            .cloned()
+            .or_else(|| self.url.as_deref().map(SourceInfo::custom))
+    }
+
+    /// The configuration without its secrets, the parameter values masked
+    /// and the HTTP header removed.
+    pub fn redacted(&self) -> Self {
+        Self {
+            params: self.params.as_ref().map(|params| {
+                params
+                    .keys()
+                    .map(|name| (name.clone(), serde_yaml::Value::from(MASKED_PARAM)))
+                    .collect()
+            }),
+            http_header: None,
+            ..self.clone()
         }
     }
 }
@@ -45,7 +83,7 @@ impl<'a> RulesetManager<'a> {
         Self { path_provider }
     }
 
-    fn safe_filename(name: &str) -> String {
+    pub(crate) fn safe_filename(name: &str) -> String {
         name.replace('/', "-")
     }
 
@@ -63,7 +101,19 @@ impl<'a> RulesetManager<'a> {
         self.get_source_file_path(name).exists()
     }
 
+    pub fn is_source_disabled(&self, name: &str) -> bool {
+        self.get_disabled_file_path(name).exists()
+    }
+
     pub fn get_enabled_sources(&self) -> Result<Vec<String>> {
+        Ok(self
+            .get_enabled_source_configs()?
+            .into_iter()
+            .map(|source| source.source)
+            .collect())
+    }
+
+    pub fn get_enabled_source_configs(&self) -> Result<Vec<EnabledSource>> {
         let sources_dir = self.path_provider.sources_dir();
         let mut enabled = Vec::new();
 
@@ -87,7 +137,7 @@ impl<'a> RulesetManager<'a> {
                             })?;
                             let source: EnabledSource = serde_yaml::from_str(&content)
                                 .with_context(|| format!("Failed to parse {}", path.display()))?;
-                            enabled.push(source.source);
+                            enabled.push(source);
                         }
                     }
                 }
@@ -98,6 +148,15 @@ impl<'a> RulesetManager<'a> {
     }
 
     pub fn enable_source(&self, name: &str, source_info: Option<&SourceInfo>) -> Result<()> {
+        self.enable_source_with_params(name, source_info, &HashMap::new())
+    }
+
+    pub fn enable_source_with_params(
+        &self,
+        name: &str,
+        source_info: Option<&SourceInfo>,
+        params: &HashMap<String, String>,
+    ) -> Result<()> {
         // Check if the ruleset is obsolete
         if let Some(info) = source_info {
             if let Some(obsolete_msg) = &info.obsolete {
@@ -107,6 +166,7 @@ impl<'a> RulesetManager<'a> {
                     obsolete_msg
                 ));
             }
+            Self::warn_unknown_parameters(name, info, params);
         }
 
         let sources_dir = self.path_provider.sources_dir();
@@ -116,15 +176,45 @@ impl<'a> RulesetManager<'a> {
         let disabled_file = self.get_disabled_file_path(name);
 
         if source_file.exists() {
-            println!(
-                "{}: Ruleset {} is already enabled",
-                "Info".yellow(),
-                name.cyan()
-            );
+            if params.is_empty() {
+                println!(
+                    "{}: Ruleset {} is already enabled",
+                    "Info".yellow(),
+                    name.cyan()
+                );
+            } else {
+                self.update_source_params(&source_file, params)?;
+                println!("Updated parameters for ruleset: {}", name.cyan());
+            }
             return Ok(());
         }
 
         if disabled_file.exists() {
+            // Parameters saved with the disabled ruleset count as provided,
+            // only those still missing are prompted for.
+            let params = match source_info {
+                Some(info) => {
+                    let content = fs::read_to_string(&disabled_file).with_context(|| {
+                        format!(
+                            "Failed to read {}: permission denied",
+                            disabled_file.display()
+                        )
+                    })?;
+                    let disabled: EnabledSource = serde_yaml::from_str(&content)
+                        .with_context(|| format!("Failed to parse {}", disabled_file.display()))?;
+                    let saved: Vec<String> =
+                        disabled.params.unwrap_or_default().into_keys().collect();
+                    let mut known = params.clone();
+                    for key in &saved {
+                        known.entry(key.clone()).or_default();
+                    }
+                    Self::prompt_parameters(name, info, &known)?
+                        .into_iter()
+                        .filter(|(key, _)| params.contains_key(key) || !saved.contains(key))
+                        .collect()
+                }
+                None => params.clone(),
+            };
             fs::rename(&disabled_file, &source_file).with_context(|| {
                 format!(
                     "Failed to re-enable source {}: permission denied for file {}",
@@ -132,16 +222,25 @@ impl<'a> RulesetManager<'a> {
                     disabled_file.display()
                 )
             })?;
+            if !params.is_empty() {
+                self.update_source_params(&source_file, &params)?;
+            }
             println!("Re-enabled previously disabled ruleset: {}", name.cyan());
         } else {
-            let enabled_source = EnabledSource::new(name.to_string());
-            let yaml = serde_yaml::to_string(&enabled_source)?;
-            fs::write(&source_file, yaml).with_context(|| {
-                format!(
-                    "Failed to write source file {}: permission denied",
-                    source_file.display()
-                )
-            })?;
+            let params = match source_info {
+                Some(info) => Self::prompt_parameters(name, info, params)?,
+                None => params.clone(),
+            };
+            let mut enabled_source = EnabledSource::new(name.to_string());
+            if !params.is_empty() {
+                enabled_source.params = Some(
+                    params
+                        .into_iter()
+                        .map(|(key, value)| (key, serde_yaml::Value::String(value)))
+                        .collect(),
+                );
+            }
+            self.write_source_file(&source_file, &enabled_source)?;
             println!("Enabled ruleset: {}", name.cyan());
         }
 
@@ -152,16 +251,161 @@ impl<'a> RulesetManager<'a> {
             println!("  Summary: {}", info.summary.bright_black());
         }
 
+        // The default source is enabled along with the first source, unless
+        // the source replaces it.
+        let replaces_default = source_info
+            .and_then(|info| info.replaces.as_ref())
+            .is_some_and(|replaces| replaces.iter().any(|replaced| replaced == DEFAULT_SOURCE));
         let enabled_count = self.get_enabled_sources()?.len();
-        if enabled_count == 1 && name != "et/open" {
+        if enabled_count == 1 && name != DEFAULT_SOURCE && !replaces_default {
             self.enable_default_source()?;
         }
 
         Ok(())
     }
 
+    /// Write the configuration of a source. It may hold secrets in its
+    /// params and http-header, so it is only readable by its owner.
+    fn write_source_file(&self, path: &Path, enabled_source: &EnabledSource) -> Result<()> {
+        let yaml = serde_yaml::to_string(enabled_source)?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            // The mode only applies to new files, a file written before
+            // this change may still be world readable.
+            if path.exists() {
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+                    .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+            }
+        }
+        options
+            .open(path)
+            .and_then(|mut file| file.write_all(yaml.as_bytes()))
+            .with_context(|| {
+                format!(
+                    "Failed to write source file {}: permission denied",
+                    path.display()
+                )
+            })
+    }
+
+    fn update_source_params(&self, path: &Path, params: &HashMap<String, String>) -> Result<()> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}: permission denied", path.display()))?;
+        let mut enabled_source: EnabledSource = serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let existing = enabled_source.params.get_or_insert_with(HashMap::new);
+        for (key, value) in params {
+            existing.insert(key.clone(), serde_yaml::Value::String(value.clone()));
+        }
+        self.write_source_file(path, &enabled_source)
+    }
+
+    /// Set the minisign public key the downloads of an enabled source are
+    /// verified with.
+    pub fn set_source_public_key(&self, name: &str, public_key: &str) -> Result<()> {
+        crate::signature::parse_public_key(public_key)?;
+        let path = self.get_source_file_path(name);
+        let content =
+            fs::read_to_string(&path).with_context(|| format!("Ruleset {name} is not enabled"))?;
+        let mut enabled_source: EnabledSource = serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        enabled_source.public_key = Some(public_key.trim().to_string());
+        self.write_source_file(&path, &enabled_source)
+    }
+
+    fn warn_unknown_parameters(name: &str, info: &SourceInfo, params: &HashMap<String, String>) {
+        for key in params.keys() {
+            let known = info
+                .parameters
+                .as_ref()
+                .is_some_and(|parameters| parameters.contains_key(key));
+            if !known {
+                eprintln!(
+                    "{}: Ruleset {} does not declare parameter {}",
+                    "Warning".yellow(),
+                    name.cyan(),
+                    key
+                );
+            }
+        }
+    }
+
+    /// Returns the given parameters, prompting for any parameter declared by
+    /// the source that was not provided on the command line.
+    fn prompt_parameters(
+        name: &str,
+        info: &SourceInfo,
+        provided: &HashMap<String, String>,
+    ) -> Result<HashMap<String, String>> {
+        let mut params = provided.clone();
+        let Some(parameters) = &info.parameters else {
+            return Ok(params);
+        };
+
+        let mut missing: Vec<&String> = parameters
+            .keys()
+            .filter(|key| !params.contains_key(*key))
+            .collect();
+        missing.sort();
+
+        if missing.is_empty() {
+            return Ok(params);
+        }
+
+        if !std::io::stdin().is_terminal() {
+            let hint = missing
+                .iter()
+                .map(|key| format!("--param {key}=VALUE"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            return Err(anyhow::anyhow!(
+                "Ruleset {} requires parameters, provide them with: {}",
+                name,
+                hint
+            ));
+        }
+
+        for key in missing {
+            let prompt = Self::parameter_prompt(key, &parameters[key]);
+            let value = if Self::is_secret_parameter(key) {
+                inquire::Password::new(&format!("{prompt}:"))
+                    .without_confirmation()
+                    .with_display_mode(inquire::PasswordDisplayMode::Masked)
+                    .prompt()?
+            } else {
+                inquire::Text::new(&format!("{prompt}:")).prompt()?
+            };
+            let value = value.trim().to_string();
+            if value.is_empty() {
+                return Err(anyhow::anyhow!("No value provided for parameter {}", key));
+            }
+            params.insert(key.clone(), value);
+        }
+
+        Ok(params)
+    }
+
+    fn parameter_prompt(key: &str, parameter: &serde_yaml::Value) -> String {
+        parameter
+            .get("prompt")
+            .and_then(|prompt| prompt.as_str())
+            .map(ToOwned::to_owned)
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    fn is_secret_parameter(key: &str) -> bool {
+        let key = key.to_ascii_lowercase();
+        ["secret", "code", "key", "token", "password"]
+            .iter()
+            .any(|word| key.contains(word))
+    }
+
     fn enable_default_source(&self) -> Result<()> {
-        let default_source = "et/open";
+        let default_source = DEFAULT_SOURCE;
         if !self.is_source_enabled(default_source) {
             println!("\nEnabling default ruleset: {}", default_source.cyan());
             let enabled_source = EnabledSource::new(default_source.to_string());
@@ -177,6 +421,135 @@ impl<'a> RulesetManager<'a> {
         Ok(())
     }
 
+    /// Refuse a source that requires a newer Suricata than the given version,
+    /// unless forced.
+    pub fn check_min_version(
+        name: &str,
+        source_info: &SourceInfo,
+        suricata_version: &str,
+        force: bool,
+    ) -> Result<()> {
+        if source_info.supports_version(suricata_version) {
+            return Ok(());
+        }
+        let min_version = source_info.min_version.as_deref().unwrap_or_default();
+        if force {
+            eprintln!(
+                "{}: Ruleset {} requires Suricata {} or newer, enabling it for Suricata {} anyway",
+                "Warning".yellow(),
+                name,
+                min_version,
+                suricata_version
+            );
+            return Ok(());
+        }
+        Err(anyhow::anyhow!(
+            "Ruleset {} requires Suricata {} or newer, but the Suricata version is {}. Use --force to enable it anyway",
+            name,
+            min_version,
+            suricata_version
+        ))
+    }
+
+    /// Offer to disable the enabled sources replaced by a source. Without a
+    /// terminal to prompt on, how to disable them is printed instead.
+    pub fn disable_replaced_sources(&self, name: &str, source_info: &SourceInfo) -> Result<()> {
+        let replaced = source_info.replaces.iter().flatten();
+        for replaced in replaced.filter(|replaced| self.is_source_enabled(replaced)) {
+            if !std::io::stdin().is_terminal() {
+                println!(
+                    "{}: Ruleset {} replaces {}, disable it with: disable-ruleset {}",
+                    "Info".yellow(),
+                    name.cyan(),
+                    replaced.cyan(),
+                    replaced
+                );
+                continue;
+            }
+
+            let disable = inquire::Confirm::new(&format!(
+                "Ruleset {name} replaces {replaced}, disable {replaced}?"
+            ))
+            .with_default(true)
+            .prompt()?;
+            if disable {
+                self.disable_source(replaced)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a custom source by URL, for rule feeds that are not in the index.
+    pub fn add_source(
+        &self,
+        name: &str,
+        url: &str,
+        http_header: Option<&str>,
+        checksum: bool,
+        public_key: Option<&str>,
+    ) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(anyhow::anyhow!("Source name must not be empty"));
+        }
+        if let Some(header) = http_header {
+            UpdateManager::parse_http_header(header)?;
+        }
+        if let Some(public_key) = public_key {
+            crate::signature::parse_public_key(public_key)?;
+        }
+
+        let source_file = self.get_source_file_path(name);
+        if source_file.exists() {
+            return Err(anyhow::anyhow!(
+                "Source {} already exists, remove it first with remove-source",
+                name
+            ));
+        }
+
+        let sources_dir = self.path_provider.sources_dir();
+        crate::paths::ensure_dir_exists(&sources_dir)?;
+
+        let mut enabled_source = EnabledSource::new(name.to_string());
+        enabled_source.url = Some(url.to_string());
+        enabled_source.http_header = http_header.map(ToOwned::to_owned);
+        enabled_source.checksum = (!checksum).then_some(false);
+        enabled_source.public_key = public_key.map(|key| key.trim().to_string());
+        self.write_source_file(&source_file, &enabled_source)?;
+
+        // A previously disabled configuration is replaced by this one.
+        let disabled_file = self.get_disabled_file_path(name);
+        if disabled_file.exists() {
+            fs::remove_file(&disabled_file)
+                .with_context(|| format!("Failed to remove {}", disabled_file.display()))?;
+        }
+
+        println!("Added source: {}", name.cyan());
+        println!("  URL: {}", url.bright_black());
+
+        Ok(())
+    }
+
+    /// Remove the configuration of a source, enabled or disabled.
+    pub fn remove_source(&self, name: &str) -> Result<()> {
+        let mut removed = false;
+        for path in [
+            self.get_source_file_path(name),
+            self.get_disabled_file_path(name),
+        ] {
+            if path.exists() {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+                removed = true;
+            }
+        }
+
+        if !removed {
+            return Err(anyhow::anyhow!("Source {} is not configured", name));
+        }
+        println!("Removed source: {}", name.cyan());
+        Ok(())
+    }
+
     pub fn disable_source(&self, name: &str) -> Result<()> {
         let source_file = self.get_source_file_path(name);
         let disabled_file = self.get_disabled_file_path(name);
@@ -207,18 +580,13 @@ impl<'a> RulesetManager<'a> {
         let mut available_sources: Vec<(&String, &SourceInfo)> = source_index
             .sources
             .iter()
-            .filter(|(_, info)| {
-                info.parameters.is_none() && info.obsolete.is_none() && info.deprecated.is_none()
-            })
+            .filter(|(_, info)| info.obsolete.is_none() && info.deprecated.is_none())
             .collect();
 
         available_sources.sort_by_key(|(name, _)| name.as_str());
 
         if available_sources.is_empty() {
-            println!(
-                "{}: No sources available without parameters",
-                "Warning".yellow()
-            );
+            println!("{}: No sources available", "Warning".yellow());
             return Ok(None);
         }
 
@@ -262,6 +630,229 @@ impl<'a> RulesetManager<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct TestPaths {
+        root: PathBuf,
+    }
+
+    impl PathProvider for TestPaths {
+        fn sources_dir(&self) -> PathBuf {
+            self.root.join("sources")
+        }
+
+        fn cache_dir(&self) -> PathBuf {
+            self.root.join("cache")
+        }
+
+        fn rules_dir(&self) -> PathBuf {
+            self.root.join("rules")
+        }
+
+        fn config_dir(&self) -> PathBuf {
+            self.root.join("config")
+        }
+    }
+
+    #[test]
+    fn test_check_min_version() {
+        let mut info = SourceInfo::custom("https://example.com/rules.tar.gz");
+        info.min_version = Some("7.0.0".to_string());
+
+        assert!(RulesetManager::check_min_version("test", &info, "7.0.3", false).is_ok());
+        let err = RulesetManager::check_min_version("test", &info, "6.0.20", false).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+        assert!(RulesetManager::check_min_version("test", &info, "6.0.20", true).is_ok());
+    }
+
+    #[test]
+    fn test_enable_first_source_enables_default_source() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+
+        let paths = TestPaths {
+            root: root.join("plain"),
+        };
+        let manager = RulesetManager::new(&paths);
+        let info = SourceInfo::custom("https://example.com/rules.tar.gz");
+        manager
+            .enable_source_with_params("test/rules", Some(&info), &HashMap::new())
+            .unwrap();
+        assert!(manager.is_source_enabled("et/open"));
+
+        // A source that replaces the default source is enabled alone.
+        let paths = TestPaths {
+            root: root.join("replaces"),
+        };
+        let manager = RulesetManager::new(&paths);
+        let mut info = SourceInfo::custom("https://example.com/pro.tar.gz");
+        info.replaces = Some(vec!["et/open".to_string()]);
+        manager
+            .enable_source_with_params("et/pro", Some(&info), &HashMap::new())
+            .unwrap();
+        assert!(manager.is_source_enabled("et/pro"));
+        assert!(!manager.is_source_enabled("et/open"));
+
+        // A public key can be set on an enabled source.
+        assert!(manager.set_source_public_key("et/pro", "invalid").is_err());
+        assert!(manager
+            .set_source_public_key("et/open", crate::signature::tests::PUBLIC_KEY)
+            .is_err());
+        manager
+            .set_source_public_key("et/pro", crate::signature::tests::PUBLIC_KEY)
+            .unwrap();
+        let enabled = manager.get_enabled_source_configs().unwrap();
+        assert_eq!(
+            enabled[0].public_key.as_deref(),
+            Some(crate::signature::tests::PUBLIC_KEY)
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_reenable_checks_parameters() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = RulesetManager::new(&paths);
+
+        let mut info = SourceInfo::custom("https://example.com/%(secret-code)s/rules.tar.gz");
+        info.replaces = Some(vec!["et/open".to_string()]);
+        info.parameters = Some(HashMap::from([(
+            "secret-code".to_string(),
+            serde_yaml::Value::Null,
+        )]));
+        let params = HashMap::from([("secret-code".to_string(), "secret".to_string())]);
+        manager
+            .enable_source_with_params("et/pro", Some(&info), &params)
+            .unwrap();
+        manager.disable_source("et/pro").unwrap();
+
+        // A parameter required since the ruleset was disabled is still
+        // required, the saved ones are kept.
+        info.parameters
+            .as_mut()
+            .unwrap()
+            .insert("region".to_string(), serde_yaml::Value::Null);
+        if !std::io::stdin().is_terminal() {
+            let err = manager
+                .enable_source_with_params("et/pro", Some(&info), &HashMap::new())
+                .unwrap_err();
+            assert!(err.to_string().contains("--param region=VALUE"));
+            assert!(!manager.is_source_enabled("et/pro"));
+        }
+        let params = HashMap::from([("region".to_string(), "eu".to_string())]);
+        manager
+            .enable_source_with_params("et/pro", Some(&info), &params)
+            .unwrap();
+        let enabled = manager.get_enabled_source_configs().unwrap();
+        let saved = enabled[0].params.as_ref().unwrap();
+        assert_eq!(saved["secret-code"], "secret");
+        assert_eq!(saved["region"], "eu");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_add_and_remove_source() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = RulesetManager::new(&paths);
+
+        manager
+            .add_source(
+                "internal/feed",
+                "https://rules.example.com/feed.tar.gz",
+                Some("Authorization: Bearer abc"),
+                false,
+                Some(crate::signature::tests::PUBLIC_KEY),
+            )
+            .unwrap();
+        assert!(manager
+            .add_source(
+                "internal/feed",
+                "https://example.com/other.rules",
+                None,
+                true,
+                None
+            )
+            .is_err());
+        assert!(manager
+            .add_source(
+                "bad-header",
+                "https://example.com/x.rules",
+                Some("no colon"),
+                true,
+                None
+            )
+            .is_err());
+        assert!(manager
+            .add_source(
+                "bad-key",
+                "https://example.com/x.rules",
+                None,
+                true,
+                Some("not a key")
+            )
+            .is_err());
+
+        let enabled = manager.get_enabled_source_configs().unwrap();
+        let feed = enabled
+            .iter()
+            .find(|source| source.source == "internal/feed")
+            .unwrap();
+        assert_eq!(
+            feed.url.as_deref(),
+            Some("https://rules.example.com/feed.tar.gz")
+        );
+        assert_eq!(
+            feed.http_header.as_deref(),
+            Some("Authorization: Bearer abc")
+        );
+        assert_eq!(feed.checksum, Some(false));
+        assert_eq!(
+            feed.public_key.as_deref(),
+            Some(crate::signature::tests::PUBLIC_KEY)
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(paths.sources_dir().join("internal-feed.yaml"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // Unlike enabling a ruleset, adding a source leaves the default
+        // source alone.
+        assert!(!manager.is_source_enabled("et/open"));
+
+        // A custom source is not in the index, its information comes from
+        // its URL.
+        let index = SourceIndex::default();
+        let info = feed.source_info(&index).unwrap();
+        assert_eq!(info.url, "https://rules.example.com/feed.tar.gz");
+        assert!(EnabledSource::new("et/pro".to_string())
+            .source_info(&index)
+            .is_none());
+
+        manager.remove_source("internal/feed").unwrap();
+        assert!(!manager.is_source_enabled("internal/feed"));
+        assert!(manager.remove_source("internal/feed").is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 
     #[test]
     fn test_safe_filename() {
@@ -269,4 +860,25 @@ mod tests {
         assert_eq!(RulesetManager::<'_>::safe_filename("simple"), "simple");
         assert_eq!(RulesetManager::<'_>::safe_filename("a/b/c"), "a-b-c");
     }
+
+    #[test]
+    fn test_is_secret_parameter() {
+        assert!(RulesetManager::<'_>::is_secret_parameter("secret-code"));
+        assert!(RulesetManager::<'_>::is_secret_parameter("oinkcode"));
+        assert!(!RulesetManager::<'_>::is_secret_parameter("region"));
+    }
+
+    #[test]
+    fn test_parameter_prompt() {
+        let parameter: serde_yaml::Value =
+            serde_yaml::from_str("prompt: Emerging Threats Pro access code").unwrap();
+        assert_eq!(
+            RulesetManager::<'_>::parameter_prompt("secret-code", &parameter),
+            "Emerging Threats Pro access code"
+        );
+        assert_eq!(
+            RulesetManager::<'_>::parameter_prompt("secret-code", &serde_yaml::Value::Null),
+            "secret-code"
+        );
+    }
 }
@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: AGPL-3.0-only
+// SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
+
+//! Minisign signature verification of the sources index and source
+//! downloads. Signatures are detached, published next to the signed file
+//! with a `.sig` extension.
+
+use anyhow::{Context, Result};
+use minisign_verify::{PublicKey, Signature};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const SIGNATURE_EXTENSION: &str = ".sig";
+
+/// The URL of the signature of a file.
+pub fn signature_url(url: &str) -> String {
+    format!("{url}{SIGNATURE_EXTENSION}")
+}
+
+/// The path of the signature of a local file.
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut signature = path.as_os_str().to_owned();
+    signature.push(SIGNATURE_EXTENSION);
+    PathBuf::from(signature)
+}
+
+/// Parse a public key, given in base64 as on the second line of a minisign
+/// public key file.
+pub fn parse_public_key(key: &str) -> Result<PublicKey> {
+    PublicKey::from_base64(key.trim()).map_err(|e| anyhow::anyhow!("Invalid public key: {e}"))
+}
+
+pub fn parse_signature(content: &str) -> Result<Signature> {
+    Signature::decode(content).map_err(|e| anyhow::anyhow!("Invalid signature: {e}"))
+}
+
+/// Verify the signature of data held in memory.
+pub fn verify(public_key: &str, data: &[u8], signature: &str) -> Result<()> {
+    let public_key = parse_public_key(public_key)?;
+    let signature = parse_signature(signature)?;
+    // Legacy signatures of minisign before 0.8 sign the content itself
+    // rather than its hash, they are still accepted.
+    public_key
+        .verify(data, &signature, true)
+        .map_err(|e| anyhow::anyhow!("Signature verification failed: {e}"))
+}
+
+/// Verify the signature of a file, reading it in chunks. Legacy signatures
+/// need the whole file in memory.
+pub fn verify_file(public_key: &str, path: &Path, signature: &str) -> Result<()> {
+    let key = parse_public_key(public_key)?;
+    let parsed = parse_signature(signature)?;
+    let mut verifier = match key.verify_stream(&parsed) {
+        Ok(verifier) => verifier,
+        Err(minisign_verify::Error::UnsupportedLegacyMode) => {
+            let data =
+                fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            return verify(public_key, &data, signature);
+        }
+        Err(e) => return Err(anyhow::anyhow!("Signature verification failed: {e}")),
+    };
+
+    let mut file =
+        fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut buffer = [0; 8192];
+    loop {
+        let bytes_read = file
+            .read(&mut buffer)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if bytes_read == 0 {
+            break;
+        }
+        verifier.update(&buffer[..bytes_read]);
+    }
+    verifier
+        .finalize()
+        .map_err(|e| anyhow::anyhow!("Signature verification failed: {e}"))
+}
+
+/// Verify a local file against the signature next to it.
+pub fn verify_local_file(public_key: &str, path: &Path) -> Result<()> {
+    let signature_path = signature_path(path);
+    let signature = fs::read_to_string(&signature_path).with_context(|| {
+        format!(
+            "No signature found for {}, expected {}",
+            path.display(),
+            signature_path.display()
+        )
+    })?;
+    verify_file(public_key, path, &signature)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    // Test vectors of the minisign-verify crate, signatures of "test".
+    pub(crate) const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    pub(crate) const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+    const LEGACY_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==";
+
+    #[test]
+    fn test_verify() {
+        assert!(verify(PUBLIC_KEY, b"test", SIGNATURE).is_ok());
+        assert!(verify(PUBLIC_KEY, b"test", LEGACY_SIGNATURE).is_ok());
+        assert!(verify(PUBLIC_KEY, b"tampered", SIGNATURE).is_err());
+        assert!(verify(PUBLIC_KEY, b"test", "not a signature").is_err());
+        assert!(verify("not a key", b"test", SIGNATURE).is_err());
+    }
+
+    #[test]
+    fn test_verify_local_file() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("test.rules");
+        fs::write(&path, "test").unwrap();
+
+        // Unsigned
+        assert!(verify_local_file(PUBLIC_KEY, &path).is_err());
+
+        for signature in [SIGNATURE, LEGACY_SIGNATURE] {
+            fs::write(root.join("test.rules.sig"), signature).unwrap();
+            assert!(verify_local_file(PUBLIC_KEY, &path).is_ok());
+        }
+
+        fs::write(&path, "tampered").unwrap();
+        assert!(verify_local_file(PUBLIC_KEY, &path).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,19 +1,22 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 // SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
 
+use crate::http::{CacheValidators, HttpOptions};
 use crate::paths::PathProvider;
-use crate::user_agent::UserAgent;
+use crate::rulesets::RulesetManager;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use colored::Colorize;
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::debug;
 
 const DEFAULT_INDEX_URL: &str = "https://www.openinfosecfoundation.org/rules/index.yaml";
 const INDEX_FILENAME: &str = "index.yaml";
+const INDEXES_FILE: &str = "indexes.yaml";
 const CACHE_MIN_AGE_SECS: i64 = 900; // 15 minutes
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -40,21 +43,245 @@ pub struct SourceInfo {
     pub deprecated: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub obsolete: Option<String>,
+    /// Minisign public key the downloads of the source are verified with.
+    /// Only honored from a signed index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "public-key")]
+    pub public_key: Option<String>,
+}
+
+impl SourceInfo {
+    /// The information for a custom source added by URL with add-source,
+    /// which is not in the index.
+    pub fn custom(url: &str) -> Self {
+        Self {
+            vendor: "Custom".to_string(),
+            summary: "Custom source".to_string(),
+            url: url.to_string(),
+            description: None,
+            license: None,
+            homepage: None,
+            min_version: None,
+            checksum: None,
+            parameters: None,
+            replaces: None,
+            deprecated: None,
+            obsolete: None,
+            public_key: None,
+        }
+    }
+
+    /// Whether the source takes parameters, such as a secret code, that
+    /// must be given when enabling it.
+    pub fn requires_parameters(&self) -> bool {
+        self.parameters
+            .as_ref()
+            .is_some_and(|parameters| !parameters.is_empty())
+    }
+
+    /// The names of the parameters of the source, sorted.
+    pub fn parameter_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .parameters
+            .iter()
+            .flat_map(|parameters| parameters.keys().map(String::as_str))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Whether the source supports the given Suricata version, according to
+    /// its min-version.
+    pub fn supports_version(&self, version: &str) -> bool {
+        self.min_version
+            .as_deref()
+            .is_none_or(|min_version| parse_version(version) >= parse_version(min_version))
+    }
+
+    /// Case-insensitive search of the name, vendor, summary, description
+    /// and license of the source.
+    fn matches(&self, name: &str, search: &str) -> bool {
+        let search = search.to_lowercase();
+        [
+            Some(name),
+            Some(self.vendor.as_str()),
+            Some(self.summary.as_str()),
+            self.description.as_deref(),
+            self.license.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|field| field.to_lowercase().contains(&search))
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The numeric components of a version such as 7.0.3 or 8.0.0-dev, without
+/// trailing zeros so 7.0 and 7.0.0 compare equal.
+fn parse_version(version: &str) -> Vec<u64> {
+    let mut components: Vec<u64> = version
+        .trim()
+        .split('.')
+        .map(|component| {
+            let digits: String = component
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            digits.parse().unwrap_or(0)
+        })
+        .collect();
+    while components.last() == Some(&0) {
+        components.pop();
+    }
+    components
+}
+
+/// Filters for listing the sources of the index.
+#[derive(Debug, Default)]
+pub struct SourceFilter {
+    /// Only list enabled sources, including deprecated and obsolete ones.
+    pub enabled: bool,
+    /// Include deprecated and obsolete sources.
+    pub all: bool,
+    /// Text to search for in the name, vendor, summary, description and
+    /// license.
+    pub search: Option<String>,
+}
+
+/// A source of the index and its status, as listed by list-sources.
+#[derive(Debug, Serialize)]
+pub struct SourceListing {
+    pub name: String,
+    #[serde(flatten)]
+    pub info: SourceInfo,
+    pub enabled: bool,
+    /// Previously enabled and since disabled.
+    pub disabled: bool,
+    #[serde(rename = "requires-parameters")]
+    pub requires_parameters: bool,
+    /// The URL of the index the source came from, None for custom sources.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+}
+
+/// List the sources of the index, and the enabled custom sources, matching
+/// the filter, sorted by name.
+pub fn list_sources(
+    index: &SourceIndex,
+    ruleset_manager: &RulesetManager,
+    filter: &SourceFilter,
+) -> Result<Vec<SourceListing>> {
+    let custom_sources: Vec<(String, SourceInfo)> = ruleset_manager
+        .get_enabled_source_configs()?
+        .into_iter()
+        .filter(|enabled_source| !index.sources.contains_key(&enabled_source.source))
+        .filter_map(|enabled_source| {
+            let info = enabled_source.source_info(index)?;
+            Some((enabled_source.source, info))
+        })
+        .collect();
+
+    let mut listings: Vec<SourceListing> = index
+        .sources
+        .iter()
+        .chain(custom_sources.iter().map(|(name, info)| (name, info)))
+        .filter(|(name, info)| {
+            filter
+                .search
+                .as_deref()
+                .is_none_or(|search| info.matches(name, search))
+        })
+        .map(|(name, info)| {
+            let enabled = ruleset_manager.is_source_enabled(name);
+            SourceListing {
+                name: name.clone(),
+                info: info.clone(),
+                enabled,
+                disabled: !enabled && ruleset_manager.is_source_disabled(name),
+                requires_parameters: info.requires_parameters(),
+                index: index.origin(name).map(ToOwned::to_owned),
+            }
+        })
+        .filter(|listing| {
+            if filter.enabled {
+                listing.enabled
+            } else {
+                filter.all || (listing.info.deprecated.is_none() && listing.info.obsolete.is_none())
+            }
+        })
+        .collect();
+    listings.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(listings)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct SourceIndex {
     pub version: u32,
     pub sources: HashMap<String, SourceInfo>,
+    /// The URL of the index each source came from, when merged from the
+    /// configured indexes.
+    #[serde(skip)]
+    pub origins: HashMap<String, String>,
+}
+
+impl SourceIndex {
+    /// Merge another index into this one, its sources replacing those of
+    /// the same name.
+    pub fn merge(&mut self, other: SourceIndex, origin: &str) {
+        self.version = self.version.max(other.version);
+        for (name, info) in other.sources {
+            self.origins.insert(name.clone(), origin.to_string());
+            self.sources.insert(name, info);
+        }
+    }
+
+    /// The URL of the index the source came from.
+    pub fn origin(&self, name: &str) -> Option<&str> {
+        self.origins.get(name).map(String::as_str)
+    }
+}
+
+/// The additional indexes merged over the default index, stored next to the
+/// sources directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexConfig {
+    #[serde(default)]
+    indexes: Vec<IndexEntry>,
+}
+
+/// A source index, and the public key of its signature if it must be
+/// verified.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub url: String,
+    #[serde(rename = "public-key", skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// An index as read or downloaded, with the content and signature it was
+/// verified with.
+struct FetchedIndex {
+    index: SourceIndex,
+    content: String,
+    signature: Option<String>,
+    validators: CacheValidators,
 }
 
 pub struct SourceManager<'a> {
     path_provider: &'a dyn PathProvider,
+    http: HttpOptions,
 }
 
 impl<'a> SourceManager<'a> {
     pub fn new(path_provider: &'a dyn PathProvider) -> Self {
-        Self { path_provider }
+        Self {
+            path_provider,
+            http: HttpOptions::default(),
+        }
+    }
+
+    pub fn with_http_options(mut self, http: HttpOptions) -> Self {
+        self.http = http;
+        self
     }
 
     pub fn get_index_path(&self) -> PathBuf {
@@ -65,52 +292,296 @@ impl<'a> SourceManager<'a> {
         std::env::var("SOURCE_INDEX_URL").unwrap_or_else(|_| DEFAULT_INDEX_URL.to_string())
     }
 
-    pub fn read_local_index(&self) -> Result<Option<SourceIndex>> {
-        let index_path = self.get_index_path();
-        if !index_path.exists() {
+    /// The minisign public key the default index is verified with, if set.
+    pub fn get_source_index_public_key(&self) -> Option<String> {
+        std::env::var("SOURCE_INDEX_PUBLIC_KEY")
+            .ok()
+            .filter(|key| !key.trim().is_empty())
+    }
+
+    fn get_index_config_path(&self) -> PathBuf {
+        self.path_provider
+            .sources_dir()
+            .with_file_name(INDEXES_FILE)
+    }
+
+    fn load_index_config(&self) -> Result<IndexConfig> {
+        let path = self.get_index_config_path();
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(IndexConfig::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn save_index_config(&self, config: &IndexConfig) -> Result<()> {
+        let path = self.get_index_config_path();
+        if let Some(parent) = path.parent() {
+            crate::paths::ensure_dir_exists(parent)?;
+        }
+        let yaml = serde_yaml::to_string(config)?;
+        fs::write(&path, yaml).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The indexes in the order they are merged: the default index followed
+    /// by the indexes added with add-index.
+    pub fn get_indexes(&self) -> Result<Vec<IndexEntry>> {
+        let mut indexes = vec![IndexEntry {
+            url: self.get_source_index_url(),
+            public_key: self.get_source_index_public_key(),
+        }];
+        indexes.extend(self.load_index_config()?.indexes);
+        Ok(indexes)
+    }
+
+    /// The cache path of an index. The default index keeps index.yaml, where
+    /// bundles also install their index, unless it has a public key. Others
+    /// are cached by the hash of their URL and public key, so an index cached
+    /// before its key was configured is not used.
+    fn get_index_cache_path(&self, entry: &IndexEntry) -> PathBuf {
+        if entry.url == self.get_source_index_url() && entry.public_key.is_none() {
+            return self.get_index_path();
+        }
+        let identity = match &entry.public_key {
+            Some(public_key) => format!("{}\n{}", entry.url, public_key),
+            None => entry.url.clone(),
+        };
+        let hash = format!("{:x}", md5::compute(identity.as_bytes()));
+        self.path_provider
+            .cache_dir()
+            .join(format!("index-{hash}.yaml"))
+    }
+
+    /// Whether the index is cached. The cached copy of an index with a
+    /// public key must still match the signature saved next to it.
+    fn is_index_cached(&self, entry: &IndexEntry) -> Result<bool> {
+        let path = self.get_index_cache_path(entry);
+        if !path.exists() {
+            return Ok(false);
+        }
+        let Some(public_key) = &entry.public_key else {
+            return Ok(true);
+        };
+        match crate::signature::verify_local_file(public_key, &path) {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                eprintln!(
+                    "{}: Ignoring cached index {}: {:#}",
+                    "Warning".yellow(),
+                    entry.url,
+                    e
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    pub fn add_index(&self, url: &str, public_key: Option<&str>) -> Result<()> {
+        if let Some(public_key) = public_key {
+            crate::signature::parse_public_key(public_key)?;
+        }
+
+        // Plain paths are stored absolute so they work from any directory.
+        let url = match crate::paths::local_url_path(url)? {
+            Some(path) if !url.contains("://") => fs::canonicalize(&path)
+                .with_context(|| format!("Index {} not found", path.display()))?
+                .display()
+                .to_string(),
+            _ => url.to_string(),
+        };
+
+        let mut config = self.load_index_config()?;
+        if url == self.get_source_index_url() || config.indexes.iter().any(|i| i.url == url) {
+            println!("{}: Index {} is already configured", "Info".yellow(), url);
+            return Ok(());
+        }
+
+        config.indexes.push(IndexEntry {
+            url: url.clone(),
+            public_key: public_key.map(|key| key.trim().to_string()),
+        });
+        self.save_index_config(&config)?;
+        println!("{}: Added index {}", "Success".green(), url);
+        println!("Run update-sources to download the index.");
+        Ok(())
+    }
+
+    pub fn remove_index(&self, url: &str) -> Result<()> {
+        let mut config = self.load_index_config()?;
+        let Some(position) = config.indexes.iter().position(|index| index.url == url) else {
+            return Err(anyhow::anyhow!("Index {} is not configured", url));
+        };
+        let entry = config.indexes.remove(position);
+        self.save_index_config(&config)?;
+
+        let cache_path = self.get_index_cache_path(&entry);
+        for path in [
+            cache_path.clone(),
+            crate::signature::signature_path(&cache_path),
+        ] {
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+                }
+            }
+        }
+        CacheValidators::remove(&cache_path)?;
+        println!("{}: Removed index {}", "Success".green(), url);
+        Ok(())
+    }
+
+    fn read_index_file(path: &Path) -> Result<Option<SourceIndex>> {
+        if !path.exists() {
             return Ok(None);
         }
 
-        let content = fs::read_to_string(&index_path).with_context(|| {
+        let content = fs::read_to_string(path).with_context(|| {
             format!(
                 "Failed to read index from {}: permission denied",
-                index_path.display()
+                path.display()
             )
         })?;
-        let index: SourceIndex = serde_yaml::from_str(&content)?;
+        let index: SourceIndex = serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse index {}", path.display()))?;
         Ok(Some(index))
     }
 
+    /// Read the cached indexes and merge them in order, later indexes
+    /// overriding the sources of earlier ones. Returns None if no index has
+    /// been cached.
+    pub fn read_local_index(&self) -> Result<Option<SourceIndex>> {
+        let mut merged: Option<SourceIndex> = None;
+        for entry in self.get_indexes()? {
+            if !self.is_index_cached(&entry)? {
+                continue;
+            }
+            let Some(mut index) = Self::read_index_file(&self.get_index_cache_path(&entry))? else {
+                continue;
+            };
+            // Only a signed index can set the public keys of its sources.
+            if entry.public_key.is_none() {
+                for source in index.sources.values_mut() {
+                    source.public_key = None;
+                }
+            }
+            // Only a local index can point its sources at local files.
+            if crate::paths::local_url_path(&entry.url)?.is_none() {
+                index.sources.retain(|name, source| {
+                    let local = matches!(crate::paths::local_url_path(&source.url), Ok(Some(_)));
+                    if local {
+                        eprintln!(
+                            "{}: Ignoring source {} of remote index {}, it points at local file {}",
+                            "Warning".yellow(),
+                            name,
+                            entry.url,
+                            source.url
+                        );
+                    }
+                    !local
+                });
+            }
+            merged
+                .get_or_insert_with(SourceIndex::default)
+                .merge(index, &entry.url);
+        }
+        Ok(merged)
+    }
+
     pub fn get_index(&self) -> Result<Option<SourceIndex>> {
         self.read_local_index()
     }
 
     pub fn get_or_download_index(&self) -> Result<SourceIndex> {
-        match self.get_index()? {
-            Some(index) => Ok(index),
-            None => {
-                println!("No sources index found, downloading...");
-                self.update_sources()?;
-                self.get_index()?.ok_or_else(|| {
-                    anyhow::anyhow!("Failed to retrieve index after updating sources")
-                })
-            }
+        let mut missing = false;
+        for entry in self.get_indexes()? {
+            missing |= !self.is_index_cached(&entry)?;
+        }
+        if missing {
+            println!("No sources index found, downloading...");
+            self.update_sources()?;
         }
+        self.get_index()?
+            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve index after updating sources"))
     }
 
     pub fn download_index(&self) -> Result<SourceIndex> {
-        let url = self.get_source_index_url();
-        println!("Downloading {}", url.cyan());
-
-        let user_agent = UserAgent::new().to_string();
-        debug!("Using User-Agent: {}", user_agent);
-        let client = reqwest::blocking::Client::builder()
-            .user_agent(user_agent)
-            .build()?;
-        let response = client
-            .get(&url)
-            .send()
+        let fetched = self
+            .fetch_index(&CacheValidators::default(), false)?
+            .ok_or_else(|| anyhow::anyhow!("Unexpected Not Modified response for the index"))?;
+        Ok(fetched.index)
+    }
+
+    /// Download the index, returning None if the server responded that the
+    /// cached index, described by the validators, has not been modified.
+    fn fetch_index(
+        &self,
+        validators: &CacheValidators,
+        quiet: bool,
+    ) -> Result<Option<FetchedIndex>> {
+        self.fetch_index_from(
+            &self.get_source_index_url(),
+            self.get_source_index_public_key().as_deref(),
+            validators,
+            quiet,
+        )
+    }
+
+    /// Download the index from the given URL, or read it if it is a local
+    /// file. With a public key the index must have a valid signature.
+    fn fetch_index_from(
+        &self,
+        url: &str,
+        public_key: Option<&str>,
+        validators: &CacheValidators,
+        quiet: bool,
+    ) -> Result<Option<FetchedIndex>> {
+        if let Some(path) = crate::paths::local_url_path(url)? {
+            if !quiet {
+                println!("Reading {}", path.display().to_string().cyan());
+            }
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read index {}", path.display()))?;
+            let signature = match public_key {
+                Some(public_key) => {
+                    let signature_path = crate::signature::signature_path(&path);
+                    let signature = fs::read_to_string(&signature_path).with_context(|| {
+                        format!(
+                            "Refusing index {}, no signature found at {}",
+                            path.display(),
+                            signature_path.display()
+                        )
+                    })?;
+                    crate::signature::verify(public_key, content.as_bytes(), &signature)
+                        .with_context(|| format!("Refusing index {}", path.display()))?;
+                    Some(signature)
+                }
+                None => None,
+            };
+            let index: SourceIndex = serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse index {}", path.display()))?;
+            return Ok(Some(FetchedIndex {
+                index,
+                content,
+                signature,
+                validators: CacheValidators::default(),
+            }));
+        }
+        if !quiet {
+            println!("Downloading {}", url.cyan());
+        }
+
+        let client = self.http.build_client()?;
+        let response = self
+            .http
+            .send(validators.apply(client.get(url)))
             .with_context(|| format!("Failed to download from {url}"))?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            debug!("Index {} not modified", url);
+            return Ok(None);
+        }
         if !response.status().is_success() {
             return Err(anyhow::anyhow!(
                 "Failed to download index: HTTP {}",
@@ -118,14 +589,71 @@ impl<'a> SourceManager<'a> {
             ));
         }
 
+        let validators = CacheValidators::from_headers(response.headers());
         let content = response.text()?;
+        let signature = match public_key {
+            Some(public_key) => {
+                let signature_url = crate::signature::signature_url(url);
+                let response = self
+                    .http
+                    .send(client.get(&signature_url))
+                    .with_context(|| format!("Failed to download from {signature_url}"))?;
+                if !response.status().is_success() {
+                    return Err(anyhow::anyhow!(
+                        "Refusing index {url}, no signature found at {signature_url}: HTTP {}",
+                        response.status()
+                    ));
+                }
+                let signature = response.text()?;
+                crate::signature::verify(public_key, content.as_bytes(), &signature)
+                    .with_context(|| format!("Refusing index {url}"))?;
+                debug!("Verified signature of {}", url);
+                Some(signature)
+            }
+            None => None,
+        };
         let index: SourceIndex = serde_yaml::from_str(&content)?;
-        Ok(index)
+        Ok(Some(FetchedIndex {
+            index,
+            content,
+            signature,
+            validators,
+        }))
     }
 
-    pub fn save_index(&self, index: &SourceIndex) -> Result<()> {
-        let index_path = self.get_index_path();
+    /// Download the index at the URL if it has changed since it was cached,
+    /// saving it and its validators. Returns the new index, or None if the
+    /// cached index is current.
+    fn download_index_if_modified(
+        &self,
+        entry: &IndexEntry,
+        quiet: bool,
+    ) -> Result<Option<SourceIndex>> {
+        let index_path = self.get_index_cache_path(entry);
+        // Validators of a cached index that no longer verifies are not sent,
+        // so it is downloaded again.
+        let validators = if self.is_index_cached(entry)? {
+            CacheValidators::load(&index_path)
+        } else {
+            CacheValidators::default()
+        };
+        let Some(fetched) =
+            self.fetch_index_from(&entry.url, entry.public_key.as_deref(), &validators, quiet)?
+        else {
+            crate::paths::touch(&index_path)?;
+            return Ok(None);
+        };
+        self.write_index(&index_path, &fetched)?;
+        if !quiet {
+            println!("Saved {}", index_path.display());
+        }
+        fetched.validators.save(&index_path)?;
+        Ok(Some(fetched.index))
+    }
 
+    /// Save the index as it was fetched, with its signature next to it, so
+    /// the cached copy can be verified again each time it is read.
+    fn write_index(&self, index_path: &Path, fetched: &FetchedIndex) -> Result<()> {
         // Ensure cache directory exists
         if let Some(parent) = index_path.parent() {
             crate::paths::ensure_dir_exists(parent).with_context(|| {
@@ -136,15 +664,25 @@ impl<'a> SourceManager<'a> {
             })?;
         }
 
-        let yaml = serde_yaml::to_string(index)?;
-        fs::write(&index_path, yaml).with_context(|| {
+        fs::write(index_path, &fetched.content).with_context(|| {
             format!(
                 "Failed to write index to {}: permission denied",
                 index_path.display()
             )
         })?;
-
-        println!("Saved {}", index_path.display());
+        let signature_path = crate::signature::signature_path(index_path);
+        match &fetched.signature {
+            Some(signature) => fs::write(&signature_path, signature)
+                .with_context(|| format!("Failed to write {}", signature_path.display()))?,
+            None => match fs::remove_file(&signature_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to remove {}", signature_path.display()))
+                }
+            },
+        }
         Ok(())
     }
 
@@ -189,100 +727,74 @@ impl<'a> SourceManager<'a> {
         // Read existing index if any
         let initial_index = self.read_local_index()?;
 
-        // Download new index
-        let new_index = self.download_index()?;
-
-        // Save the new index
-        self.save_index(&new_index)?;
-
-        // Compare and report changes
-        self.compare_sources(initial_index.as_ref(), &new_index);
+        // Download and save each index that changed
+        let mut changed = false;
+        for entry in self.get_indexes()? {
+            changed |= self.download_index_if_modified(&entry, false)?.is_some();
+        }
+        match self.read_local_index()? {
+            Some(new_index) if changed => self.compare_sources(initial_index.as_ref(), &new_index),
+            _ => println!("{}", "No change in sources".yellow()),
+        }
 
         Ok(())
     }
 
     pub fn update_sources_cached(&self, force: bool, quiet: bool) -> Result<()> {
-        let index_path = self.get_index_path();
-
-        // Check if we have a recent cache (unless force is specified)
-        if !force && index_path.exists() {
-            if let Ok(metadata) = fs::metadata(&index_path) {
-                if let Ok(modified) = metadata.modified() {
-                    let age = Utc::now()
-                        .signed_duration_since(DateTime::<Utc>::from(modified))
-                        .num_seconds();
+        // Read existing index if any
+        let initial_index = self.read_local_index()?;
+
+        let mut changed = false;
+        for entry in self.get_indexes()? {
+            let url = &entry.url;
+            // Check if we have a recent cache (unless force is specified). A
+            // local index is always read again.
+            let index_path = self.get_index_cache_path(&entry);
+            let local = crate::paths::local_url_path(url)?.is_some();
+            if !force && !local && self.is_index_cached(&entry)? {
+                if let Some(age) = cache_age(&index_path) {
                     if age < CACHE_MIN_AGE_SECS {
                         if !quiet {
                             println!(
-                                "  Using cached sources index (age: {} seconds)",
+                                "  Using cached sources index {} (age: {} seconds)",
+                                url.cyan(),
                                 age.to_string().bright_black()
                             );
                         }
-                        return Ok(());
+                        continue;
                     }
                 }
             }
-        }
-
-        // Read existing index if any
-        let initial_index = self.read_local_index()?;
-
-        // Download new index
-        let new_index = if quiet {
-            // Suppress download message in quiet mode
-            let url = self.get_source_index_url();
-            let user_agent = UserAgent::new().to_string();
-            debug!("Using User-Agent: {}", user_agent);
-            let client = reqwest::blocking::Client::builder()
-                .user_agent(user_agent)
-                .build()?;
-            let response = client
-                .get(&url)
-                .send()
-                .with_context(|| format!("Failed to download from {url}"))?;
-            if !response.status().is_success() {
-                return Err(anyhow::anyhow!(
-                    "Failed to download index: HTTP {}",
-                    response.status()
-                ));
-            }
-            let content = response.text()?;
-            serde_yaml::from_str(&content)?
-        } else {
-            self.download_index()?
-        };
 
-        // Save the new index
-        if quiet {
-            let index_path = self.get_index_path();
-            if let Some(parent) = index_path.parent() {
-                crate::paths::ensure_dir_exists(parent).with_context(|| {
-                    format!(
-                        "Failed to create cache directory {}: permission denied",
-                        parent.display()
-                    )
-                })?;
+            // Download and save the new index, if it changed
+            if self.download_index_if_modified(&entry, quiet)?.is_some() {
+                changed = true;
+            } else if !quiet {
+                println!("  Sources index {} has not been modified", url.cyan());
             }
-            let yaml = serde_yaml::to_string(&new_index)?;
-            fs::write(&index_path, yaml).with_context(|| {
-                format!(
-                    "Failed to write index to {}: permission denied",
-                    index_path.display()
-                )
-            })?;
-        } else {
-            self.save_index(&new_index)?;
         }
 
         // Compare and report changes (only if not quiet)
-        if !quiet {
-            self.compare_sources(initial_index.as_ref(), &new_index);
+        if changed && !quiet {
+            if let Some(new_index) = self.read_local_index()? {
+                self.compare_sources(initial_index.as_ref(), &new_index);
+            }
         }
 
         Ok(())
     }
 }
 
+/// Seconds since the file was last modified, None if it doesn't exist.
+fn cache_age(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(
+        Utc::now()
+            .signed_duration_since(DateTime::<Utc>::from(modified))
+            .num_seconds(),
+    )
+}
+
 fn sources_equal(a: &SourceInfo, b: &SourceInfo) -> bool {
     // Compare all fields that matter for detecting changes
     a.vendor == b.vendor
@@ -297,4 +809,388 @@ fn sources_equal(a: &SourceInfo, b: &SourceInfo) -> bool {
         && a.replaces == b.replaces
         && a.deprecated == b.deprecated
         && a.obsolete == b.obsolete
+        && a.public_key == b.public_key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct TestPaths {
+        root: PathBuf,
+    }
+
+    impl PathProvider for TestPaths {
+        fn sources_dir(&self) -> PathBuf {
+            self.root.join("sources")
+        }
+
+        fn cache_dir(&self) -> PathBuf {
+            self.root.join("cache")
+        }
+
+        fn rules_dir(&self) -> PathBuf {
+            self.root.join("rules")
+        }
+
+        fn config_dir(&self) -> PathBuf {
+            self.root.join("config")
+        }
+    }
+
+    fn test_index() -> SourceIndex {
+        serde_yaml::from_str(
+            r#"
+version: 1
+sources:
+  et/open:
+    vendor: Proofpoint
+    summary: Emerging Threats Open Ruleset
+    url: https://rules.emergingthreats.net/open/suricata-%(__version__)s/emerging.rules.tar.gz
+    license: MIT
+  et/pro:
+    vendor: Proofpoint
+    summary: Emerging Threats Pro Ruleset
+    url: https://rules.emergingthreatspro.com/%(secret-code)s/suricata-%(__version__)s/etpro.rules.tar.gz
+    license: Commercial
+    parameters:
+      secret-code:
+        prompt: Emerging Threats Pro access code
+  oisf/trafficid:
+    vendor: OISF
+    summary: Suricata Traffic ID ruleset
+    url: https://openinfosecfoundation.org/rules/trafficid/trafficid.rules
+    license: MIT
+    obsolete: Included in Suricata
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_supports_version() {
+        let mut info = SourceInfo::custom("https://example.com/rules.tar.gz");
+        assert!(info.supports_version("4.1.0"));
+
+        info.min_version = Some("7.0.0".to_string());
+        assert!(info.supports_version("7.0.0"));
+        assert!(info.supports_version("7.0"));
+        assert!(info.supports_version("7.0.3"));
+        assert!(info.supports_version("8.0.0-dev"));
+        assert!(info.supports_version("10.0.0"));
+        assert!(!info.supports_version("6.0.20"));
+    }
+
+    #[test]
+    fn test_local_index() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+        let index_path = root.join("index.yaml");
+        fs::write(&index_path, serde_yaml::to_string(&test_index()).unwrap()).unwrap();
+
+        let manager = SourceManager::new(&paths);
+        for url in [
+            format!("file://{}", index_path.display()),
+            index_path.display().to_string(),
+        ] {
+            let fetched = manager
+                .fetch_index_from(&url, None, &CacheValidators::default(), true)
+                .unwrap()
+                .unwrap();
+            assert_eq!(fetched.index.sources.len(), 3);
+            assert!(fetched.validators.is_empty());
+        }
+        assert!(manager
+            .fetch_index_from(
+                &format!("file://{}", root.join("missing.yaml").display()),
+                None,
+                &CacheValidators::default(),
+                true
+            )
+            .is_err());
+
+        // With a public key the index is refused without a valid signature.
+        let url = index_path.display().to_string();
+        let public_key = Some(crate::signature::tests::PUBLIC_KEY);
+        assert!(manager
+            .fetch_index_from(&url, public_key, &CacheValidators::default(), true)
+            .is_err());
+        fs::write(
+            root.join("index.yaml.sig"),
+            crate::signature::tests::SIGNATURE,
+        )
+        .unwrap();
+        assert!(manager
+            .fetch_index_from(&url, public_key, &CacheValidators::default(), true)
+            .is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    // Key and signature of the index with the "signed" source written by
+    // test_source_public_keys_only_from_signed_index.
+    const INDEX_PUBLIC_KEY: &str = "RWQHAQIDBAUGCAOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+    const INDEX_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQHAQIDBAUGCPig3Y9VGrbUE1G4l/ulVvNLDKVLjCzLqkjnC4V1CKfp0OWV4YAMZN6k5RA5tA47uBtQyhkqtQ7HAHS3XL8OKQI=
+trusted comment: timestamp:1760572800\tfile:index.yaml
+zQDNGu+V1kB1mWopQ7NeFLi/EUnS2gAQIfTQ4iFDGUKGgLowDcl2C0GOjp+jDyqgkV5SsqOh0xW34uEcQ4FGBw==";
+
+    #[test]
+    fn test_source_public_keys_only_from_signed_index() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = SourceManager::new(&paths);
+        let public_key = crate::signature::tests::PUBLIC_KEY;
+
+        let index = |name: &str| {
+            format!(
+                "version: 1\nsources:\n  {name}:\n    vendor: Test\n    summary: Test\n    url: https://example.com/{name}.tar.gz\n    public-key: {public_key}\n"
+            )
+        };
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+        fs::write(manager.get_index_path(), index("unsigned")).unwrap();
+        manager
+            .add_index("https://example.com/index.yaml", Some(INDEX_PUBLIC_KEY))
+            .unwrap();
+        let signed = IndexEntry {
+            url: "https://example.com/index.yaml".to_string(),
+            public_key: Some(INDEX_PUBLIC_KEY.to_string()),
+        };
+        let signed_path = manager.get_index_cache_path(&signed);
+        fs::write(&signed_path, index("signed")).unwrap();
+        fs::write(
+            crate::signature::signature_path(&signed_path),
+            INDEX_SIGNATURE,
+        )
+        .unwrap();
+
+        let merged = manager.read_local_index().unwrap().unwrap();
+        assert_eq!(merged.sources["unsigned"].public_key, None);
+        assert_eq!(
+            merged.sources["signed"].public_key.as_deref(),
+            Some(public_key)
+        );
+
+        // A cached copy that no longer matches its signature is not used.
+        fs::write(&signed_path, index("tampered")).unwrap();
+        let merged = manager.read_local_index().unwrap().unwrap();
+        assert!(!merged.sources.contains_key("tampered"));
+        assert!(!manager.is_index_cached(&signed).unwrap());
+
+        // The default index is cached apart once it has a public key.
+        let default = IndexEntry {
+            url: manager.get_source_index_url(),
+            public_key: Some(INDEX_PUBLIC_KEY.to_string()),
+        };
+        assert_ne!(
+            manager.get_index_cache_path(&default),
+            manager.get_index_path()
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_local_sources_only_from_local_index() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = SourceManager::new(&paths);
+
+        let index = |name: &str| {
+            format!(
+                "version: 1\nsources:\n  {name}/local:\n    vendor: Test\n    summary: Test\n    url: file:///srv/rules/{name}.rules\n  {name}/remote:\n    vendor: Test\n    summary: Test\n    url: https://example.com/{name}.rules\n"
+            )
+        };
+        // The default index is remote, the team index a local file.
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+        fs::write(manager.get_index_path(), index("default")).unwrap();
+        let team_path = root.join("team.yaml");
+        fs::write(&team_path, index("team")).unwrap();
+        manager
+            .add_index(&team_path.display().to_string(), None)
+            .unwrap();
+        manager.update_sources_cached(false, true).unwrap();
+
+        let index = manager.read_local_index().unwrap().unwrap();
+        assert!(!index.sources.contains_key("default/local"));
+        assert!(index.sources.contains_key("default/remote"));
+        assert!(index.sources.contains_key("team/local"));
+        assert!(index.sources.contains_key("team/remote"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_merged_indexes() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = SourceManager::new(&paths);
+
+        // A fresh cache of the default index is used without downloading.
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+        fs::write(
+            manager.get_index_path(),
+            serde_yaml::to_string(&test_index()).unwrap(),
+        )
+        .unwrap();
+        let team_path = root.join("team.yaml");
+        fs::write(
+            &team_path,
+            r#"
+version: 1
+sources:
+  et/open:
+    vendor: Team
+    summary: Emerging Threats Open, mirrored
+    url: https://rules.example.com/et-open.tar.gz
+  team/feed:
+    vendor: Team
+    summary: Team feed
+    url: https://rules.example.com/feed.tar.gz
+"#,
+        )
+        .unwrap();
+
+        manager
+            .add_index(&team_path.display().to_string(), None)
+            .unwrap();
+        let team_url = fs::canonicalize(&team_path).unwrap().display().to_string();
+        let team = IndexEntry {
+            url: team_url.clone(),
+            public_key: None,
+        };
+        let indexes = manager.get_indexes().unwrap();
+        assert_eq!(indexes.len(), 2);
+        assert_eq!(indexes[0].url, manager.get_source_index_url());
+        assert_eq!(indexes[1], team);
+
+        manager.update_sources_cached(false, true).unwrap();
+        let team_cache = manager.get_index_cache_path(&team);
+        assert!(team_cache.exists());
+        // An index cached before its public key was configured is not used.
+        let signed = IndexEntry {
+            public_key: Some(crate::signature::tests::PUBLIC_KEY.to_string()),
+            ..team.clone()
+        };
+        assert_ne!(manager.get_index_cache_path(&signed), team_cache);
+
+        let index = manager.read_local_index().unwrap().unwrap();
+        assert_eq!(index.sources.len(), 4);
+        assert_eq!(index.sources["et/open"].vendor, "Team");
+        assert_eq!(index.origin("et/open"), Some(team_url.as_str()));
+        assert_eq!(
+            index.origin("et/pro"),
+            Some(manager.get_source_index_url().as_str())
+        );
+
+        let listings = list_sources(
+            &index,
+            &RulesetManager::new(&paths),
+            &SourceFilter::default(),
+        )
+        .unwrap();
+        let feed = listings.iter().find(|l| l.name == "team/feed").unwrap();
+        assert_eq!(feed.index.as_deref(), Some(team_url.as_str()));
+        assert_eq!(serde_json::to_value(feed).unwrap()["index"], team_url);
+
+        manager.remove_index(&team_url).unwrap();
+        assert!(!team_cache.exists());
+        assert!(manager.remove_index(&team_url).is_err());
+        let index = manager.read_local_index().unwrap().unwrap();
+        assert_eq!(index.sources["et/open"].vendor, "Proofpoint");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_list_sources() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        fs::create_dir_all(paths.sources_dir()).unwrap();
+        fs::write(
+            paths.sources_dir().join("et-open.yaml"),
+            "source: et/open\n",
+        )
+        .unwrap();
+        fs::write(
+            paths.sources_dir().join("oisf-trafficid.yaml"),
+            "source: oisf/trafficid\n",
+        )
+        .unwrap();
+        fs::write(
+            paths.sources_dir().join("et-pro.yaml.disabled"),
+            "source: et/pro\n",
+        )
+        .unwrap();
+
+        let index = test_index();
+        let ruleset_manager = RulesetManager::new(&paths);
+        let names = |filter: SourceFilter| -> Vec<String> {
+            list_sources(&index, &ruleset_manager, &filter)
+                .unwrap()
+                .into_iter()
+                .map(|listing| listing.name)
+                .collect()
+        };
+
+        assert_eq!(names(SourceFilter::default()), vec!["et/open", "et/pro"]);
+        assert_eq!(
+            names(SourceFilter {
+                all: true,
+                ..Default::default()
+            }),
+            vec!["et/open", "et/pro", "oisf/trafficid"]
+        );
+        assert_eq!(
+            names(SourceFilter {
+                enabled: true,
+                ..Default::default()
+            }),
+            vec!["et/open", "oisf/trafficid"]
+        );
+        assert_eq!(
+            names(SourceFilter {
+                search: Some("COMMERCIAL".to_string()),
+                ..Default::default()
+            }),
+            vec!["et/pro"]
+        );
+
+        let listings = list_sources(&index, &ruleset_manager, &SourceFilter::default()).unwrap();
+        let pro = &listings[1];
+        assert!(!pro.enabled);
+        assert!(pro.disabled);
+        assert!(pro.requires_parameters);
+        assert_eq!(pro.info.parameter_names(), vec!["secret-code"]);
+
+        let json = serde_json::to_value(&listings[0]).unwrap();
+        assert_eq!(json["name"], "et/open");
+        assert_eq!(json["vendor"], "Proofpoint");
+        assert_eq!(json["enabled"], true);
+        assert_eq!(json["requires-parameters"], false);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
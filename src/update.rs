@@ -1,65 +1,214 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 // SPDX-FileCopyrightText: Copyright 2025 Jason Ish <jason@codemonkey.net>
 
+use crate::archive::{self, ArchiveFormat, SourceFile, SourceLocation};
+use crate::flowbits;
+use crate::http::{CacheValidators, HttpOptions};
+use crate::local::{self, LocalRulesManager};
+use crate::matchers::{RuleConf, RuleConfStats, DISABLE_CONF, DROP_CONF, ENABLE_CONF, MODIFY_CONF};
+use crate::parser::{self, ParseError, RuleOption};
 use crate::paths::PathProvider;
-use crate::rulesets::RulesetManager;
+use crate::reload;
+use crate::report::RuleReport;
+use crate::rulesets::{EnabledSource, RulesetManager};
 use crate::sources::{SourceInfo, SourceManager};
-use crate::user_agent::UserAgent;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use colored::Colorize;
-use flate2::read::GzDecoder;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use regex::Regex;
+use reqwest::blocking::Response;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::StatusCode;
 use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{IsTerminal, Read, Write};
+use std::io::{BufWriter, IsTerminal, Read, Write};
 use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::OnceLock;
-use tar::Archive;
 use tracing::debug;
-use zip::ZipArchive;
 
 const DEFAULT_OUTPUT_FILE: &str = "suricata.rules";
 const DATASETS_DIR: &str = "datasets";
+const STAGING_DIR: &str = ".staging";
 const LEGACY_MANAGED_DATASETS_DIR: &str = "suricasta";
 const CACHE_MIN_AGE_SECS: i64 = 900; // 15 minutes
+/// Maximum number of sources downloaded at the same time.
+const DOWNLOAD_WORKERS: usize = 4;
+
+const DEFAULT_SURICATA: &str = "suricata";
+const SURICATA_CONF: &str = "suricata.yaml";
+/// Source name used for local rules when naming their datasets.
+const LOCAL_SOURCE: &str = "local";
 
 pub struct UpdateManager<'a> {
     path_provider: &'a dyn PathProvider,
+    /// Directory the rules and datasets are written to.
+    rules_dir: PathBuf,
+    suricata: PathBuf,
+    /// Whether the Suricata binary was given rather than found in the PATH.
+    suricata_configured: bool,
     suricata_version: String,
+    http: HttpOptions,
 }
 
-#[derive(Debug)]
-struct SourceFile {
-    filename: String,
-    content: Vec<u8>,
+#[derive(Debug, Clone, Default)]
+pub struct UpdateOptions {
+    pub force: bool,
+    pub quiet: bool,
+    /// Don't make any network requests, build the rules from the cached
+    /// index and source downloads.
+    pub offline: bool,
+    /// Abort the update without touching the output if any source fails,
+    /// instead of falling back to its last cached download. None if not
+    /// given, which doesn't abort.
+    pub fail_on_source_error: Option<bool>,
+    pub http: HttpOptions,
+    /// Directory to write the rules to instead of the default rules
+    /// directory.
+    pub output: Option<PathBuf>,
+    /// Rule configuration files, defaulting to the files of the same name in
+    /// the configuration directory.
+    pub enable_conf: Option<PathBuf>,
+    pub disable_conf: Option<PathBuf>,
+    pub drop_conf: Option<PathBuf>,
+    pub modify_conf: Option<PathBuf>,
+    /// Filename patterns of rule files in sources to ignore.
+    pub ignore: Vec<String>,
+    /// Suricata version to use instead of detecting it.
+    pub suricata_version: Option<String>,
+    /// Path to the Suricata binary, defaults to "suricata" found in the PATH.
+    pub suricata: Option<PathBuf>,
+    /// Suricata configuration used to test the rules, defaults to
+    /// suricata.yaml in the configuration directory.
+    pub suricata_conf: Option<PathBuf>,
+    /// Skip testing the rules with Suricata before installing them.
+    pub no_test: bool,
+    /// Command to run to reload Suricata when the rules have changed.
+    pub reload_command: Option<String>,
+    /// Suricata command socket to send reload-rules to when the rules have
+    /// changed.
+    pub reload_socket: Option<PathBuf>,
+    /// Write a JSON report of the rule changes to this file.
+    pub report_json: Option<PathBuf>,
+    /// Local rule files or directories of rule files, in addition to those
+    /// added with add-local.
+    pub local: Vec<PathBuf>,
+    pub disable_regexes: Vec<String>,
+    pub disable_substrings: Vec<String>,
 }
 
+/// Download parameters for a source after applying the enabled source
+/// configuration on top of the index entry.
 #[derive(Debug)]
+struct SourceRequest {
+    url: String,
+    /// The URL with the parameter values masked, for output, as parameters
+    /// such as secret-code must not end up in logs.
+    display_url: String,
+    http_header: Option<String>,
+    checksum: bool,
+    /// Minisign public key the signature of the download must verify with.
+    public_key: Option<String>,
+}
+
+impl SourceRequest {
+    /// The hash the download is cached by. It includes the public key, so a
+    /// download cached before the key was configured is not used.
+    fn cache_key(&self) -> String {
+        match &self.public_key {
+            Some(public_key) => UpdateManager::url_hash(&format!("{}\n{}", self.url, public_key)),
+            None => UpdateManager::url_hash(&self.url),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 struct ProcessedSource {
     rules: HashMap<String, Rule>,
+    /// Keys of the rules disabled by disable.conf.
+    disabled: HashSet<String>,
 }
 
 #[derive(Debug, Clone)]
-struct ResolvedDataset {
+pub(crate) struct ResolvedDataset {
     output_path: PathBuf,
     content: Vec<u8>,
 }
 
 #[derive(Debug, Clone)]
-struct Rule {
-    raw: String,
-    enabled: bool,
-    sid: u32,
-    gid: u32,
-    rev: u32,
-    group: String,
-    datasets: Vec<ResolvedDataset>,
-    #[allow(dead_code)]
-    msg: String,
+pub(crate) struct Rule {
+    pub(crate) raw: String,
+    pub(crate) enabled: bool,
+    pub(crate) action: String,
+    pub(crate) sid: u32,
+    pub(crate) gid: u32,
+    pub(crate) rev: u32,
+    pub(crate) group: String,
+    pub(crate) options: Vec<RuleOption>,
+    pub(crate) datasets: Vec<ResolvedDataset>,
+    pub(crate) msg: String,
+}
+
+impl Rule {
+    /// Parse a rule. The text must not include any leading comment
+    /// characters, a commented out rule is represented with `enabled` set
+    /// to false.
+    pub(crate) fn parse(
+        raw: &str,
+        enabled: bool,
+        group: &str,
+    ) -> std::result::Result<Self, ParseError> {
+        let parsed = parser::parse_rule(raw)?;
+        let sid = parsed
+            .sid()?
+            .ok_or_else(|| ParseError::new("missing sid"))?;
+
+        Ok(Self {
+            raw: raw.trim().to_string(),
+            enabled,
+            sid,
+            gid: parsed.gid()?.unwrap_or(1),
+            rev: parsed.rev()?.unwrap_or(1),
+            msg: parsed.msg().unwrap_or_default(),
+            action: parsed.action,
+            group: group.to_string(),
+            options: parsed.options,
+            datasets: Vec::new(),
+        })
+    }
+
+    /// Replace the rule text, re-parsing it. The rule is left unchanged if
+    /// the new text fails to parse.
+    pub(crate) fn set_raw(&mut self, raw: String) -> std::result::Result<(), ParseError> {
+        let parsed = Self::parse(&raw, self.enabled, &self.group)?;
+        *self = Self {
+            datasets: std::mem::take(&mut self.datasets),
+            ..parsed
+        };
+        Ok(())
+    }
+
+    /// The values of all options with the given name.
+    pub(crate) fn option_values<'r>(&'r self, name: &'r str) -> impl Iterator<Item = &'r str> {
+        self.options
+            .iter()
+            .filter(move |option| option.name == name)
+            .filter_map(|option| option.value.as_deref())
+    }
+
+    /// Replace an alert action with the given action, returning true if the
+    /// rule was changed.
+    pub(crate) fn set_action(&mut self, action: &str) -> bool {
+        if self.action != "alert" {
+            return false;
+        }
+        self.raw = format!("{}{}", action, &self.raw[self.action.len()..]);
+        self.action = action.to_string();
+        true
+    }
 }
 
 #[derive(Debug, Default)]
@@ -97,6 +246,45 @@ impl RuleFilters {
     }
 }
 
+/// Output of the source downloads, which run concurrently. In a TTY the
+/// messages are printed above the progress bars of the downloads in flight.
+struct DownloadProgress {
+    multi: Option<MultiProgress>,
+    quiet: bool,
+}
+
+impl DownloadProgress {
+    fn new(quiet: bool) -> Self {
+        let multi = (!quiet && UpdateManager::is_tty()).then(MultiProgress::new);
+        Self { multi, quiet }
+    }
+
+    fn println(&self, message: String) {
+        if self.quiet {
+            return;
+        }
+        match &self.multi {
+            Some(multi) => {
+                let _ = multi.println(message);
+            }
+            None => println!("{message}"),
+        }
+    }
+
+    fn add_bar(&self, source_name: &str, content_length: u64) -> Option<ProgressBar> {
+        let multi = self.multi.as_ref()?;
+        let progress_bar = multi.add(ProgressBar::new(content_length));
+        progress_bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} {msg} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+                .unwrap()
+                .progress_chars("#>-")
+        );
+        progress_bar.set_message(source_name.to_string());
+        Some(progress_bar)
+    }
+}
+
 impl<'a> UpdateManager<'a> {
     pub fn new(path_provider: &'a dyn PathProvider) -> Self {
         Self::new_with_suricata_version(path_provider, None)
@@ -106,20 +294,50 @@ impl<'a> UpdateManager<'a> {
         path_provider: &'a dyn PathProvider,
         suricata_version: Option<&str>,
     ) -> Self {
+        Self::new_with_suricata(path_provider, None, suricata_version)
+    }
+
+    pub fn new_with_suricata(
+        path_provider: &'a dyn PathProvider,
+        suricata: Option<&Path>,
+        suricata_version: Option<&str>,
+    ) -> Self {
+        let suricata_configured = suricata.is_some();
+        let suricata = suricata
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SURICATA));
         let suricata_version = suricata_version
             .map(str::trim)
             .filter(|value| !value.is_empty())
             .map(ToOwned::to_owned)
-            .unwrap_or_else(Self::get_suricata_version);
+            .unwrap_or_else(|| Self::get_suricata_version(&suricata));
 
         Self {
             path_provider,
+            rules_dir: path_provider.rules_dir(),
+            suricata,
+            suricata_configured,
             suricata_version,
+            http: HttpOptions::default(),
         }
     }
 
-    fn get_suricata_version() -> String {
-        if let Ok(output) = std::process::Command::new("suricata").arg("-V").output() {
+    pub fn with_http_options(mut self, http: HttpOptions) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Write the rules to the given directory instead of the rules
+    /// directory of the path provider.
+    pub fn with_output_dir(mut self, output_dir: Option<&Path>) -> Self {
+        if let Some(dir) = output_dir {
+            self.rules_dir = dir.to_path_buf();
+        }
+        self
+    }
+
+    fn get_suricata_version(suricata: &Path) -> String {
+        if let Ok(output) = std::process::Command::new(suricata).arg("-V").output() {
             if output.status.success() {
                 let stdout = String::from_utf8_lossy(&output.stdout);
                 // Output looks like: "This is Suricata version 7.0.7 RELEASE"
@@ -147,6 +365,20 @@ impl<'a> UpdateManager<'a> {
         disable_regexes: &[String],
         disable_substrings: &[String],
     ) -> Result<()> {
+        self.update_with_options(&UpdateOptions {
+            force,
+            quiet,
+            disable_regexes: disable_regexes.to_vec(),
+            disable_substrings: disable_substrings.to_vec(),
+            ..Default::default()
+        })
+    }
+
+    pub fn update_with_options(&self, options: &UpdateOptions) -> Result<()> {
+        let force = options.force;
+        let quiet = options.quiet;
+        let fail_on_source_error = options.fail_on_source_error.unwrap_or(false);
+
         // Macro for conditional printing (only print if not quiet)
         macro_rules! info_println {
             ($($arg:tt)*) => {
@@ -156,18 +388,24 @@ impl<'a> UpdateManager<'a> {
             };
         }
 
-        let rule_filters = RuleFilters::new(disable_regexes, disable_substrings)?;
+        let rule_filters = RuleFilters::new(&options.disable_regexes, &options.disable_substrings)?;
+        let rule_conf = self.load_rule_conf(options)?.with_ignore(&options.ignore)?;
 
         info_println!("{}", "Running Suricata rule update...".green().bold());
 
         // First, update sources
-        let source_manager = SourceManager::new(self.path_provider);
-        info_println!("\n{}", "Updating sources...".cyan());
-        source_manager.update_sources_cached(force, quiet)?;
+        let source_manager =
+            SourceManager::new(self.path_provider).with_http_options(self.http.clone());
+        if options.offline {
+            info_println!("\n{}", "Offline, using the cached sources index".cyan());
+        } else {
+            info_println!("\n{}", "Updating sources...".cyan());
+            source_manager.update_sources_cached(force, quiet)?;
+        }
 
         // Get enabled sources
         let ruleset_manager = RulesetManager::new(self.path_provider);
-        let mut enabled_sources = ruleset_manager.get_enabled_sources()?;
+        let mut enabled_sources = ruleset_manager.get_enabled_source_configs()?;
 
         // If no sources are enabled, use et/open as a fallback without enabling it
         if enabled_sources.is_empty() {
@@ -175,23 +413,101 @@ impl<'a> UpdateManager<'a> {
                 "{}: No sources configured, will use Emerging Threats Open as fallback",
                 "Info".yellow()
             );
-            enabled_sources = vec!["et/open".to_string()];
+            enabled_sources = vec![EnabledSource::new("et/open".to_string())];
         }
 
         // Load source index (it should be fresh after update)
         let source_index = source_manager.get_index()?.ok_or_else(|| {
-            anyhow::anyhow!(
-                "No sources index found after updating sources. The index download may have failed."
-            )
+            if options.offline {
+                anyhow::anyhow!(
+                    "No cached sources index found, run update-sources while online first"
+                )
+            } else {
+                anyhow::anyhow!(
+                    "No sources index found after updating sources. The index download may have failed."
+                )
+            }
         })?;
 
-        // Download and process each enabled source
+        // Skip sources Suricata can't load rather than failing the rule test.
+        enabled_sources.retain(|enabled_source| {
+            let Some(source_info) = enabled_source.source_info(&source_index) else {
+                return true;
+            };
+            if source_info.supports_version(&self.suricata_version) {
+                return true;
+            }
+            eprintln!(
+                "{}: Skipping {}, it requires Suricata {} or newer and the target version is {}",
+                "Warning".yellow(),
+                enabled_source.source,
+                source_info.min_version.as_deref().unwrap_or_default(),
+                self.suricata_version
+            );
+            false
+        });
+
+        // Resolve the enabled sources in their configured order, None if the
+        // source is neither in the index nor a custom source with a URL.
+        let requests: Vec<(&str, Option<Result<SourceRequest>>)> = enabled_sources
+            .iter()
+            .map(|enabled_source| {
+                let request = enabled_source
+                    .source_info(&source_index)
+                    .map(|source_info| self.resolve_source(&source_info, enabled_source));
+                (enabled_source.source.as_str(), request)
+            })
+            .collect();
+
+        // Download the sources concurrently.
+        let downloads: Vec<(&str, &SourceRequest)> = requests
+            .iter()
+            .filter_map(|(source_name, request)| match request {
+                Some(Ok(request)) => Some((*source_name, request)),
+                _ => None,
+            })
+            .collect();
+        if !downloads.is_empty() {
+            info_println!("\n{}", "Downloading sources...".cyan());
+        }
+        let mut archives = self
+            .fetch_sources(&downloads, force, options.offline, quiet)
+            .into_iter();
+
+        // Process the sources in their configured order, so the merge does
+        // not depend on which download finished first.
         let mut all_rules: HashMap<String, Rule> = HashMap::new();
-        for source_name in &enabled_sources {
+        let mut disabled_rules: HashSet<String> = HashSet::new();
+        for (source_name, request) in requests {
             info_println!("\nProcessing source: {}", source_name.cyan());
 
-            if let Some(source_info) = source_index.sources.get(source_name) {
-                match self.process_source(source_name, source_info, force, quiet) {
+            if let Some(request) = request {
+                let result = request.and_then(|request| {
+                    let archive = archives.next().expect("a download for every request");
+                    archive
+                        .and_then(|location| {
+                            self.process_source_files(
+                                source_name,
+                                &request,
+                                &location,
+                                &rule_conf,
+                                quiet,
+                            )
+                        })
+                        .or_else(|err| {
+                            if options.offline || fail_on_source_error {
+                                return Err(err);
+                            }
+                            self.process_cached_source(
+                                source_name,
+                                &request,
+                                &rule_conf,
+                                err,
+                                quiet,
+                            )
+                        })
+                });
+                match result {
                     Ok(processed) => {
                         info_println!(
                             "  Loaded {} rules from {}",
@@ -199,11 +515,20 @@ impl<'a> UpdateManager<'a> {
                             source_name.cyan()
                         );
 
+                        disabled_rules.extend(processed.disabled);
+
                         // Merge rules, preferring higher revision numbers
                         for (key, rule) in processed.rules {
                             Self::insert_rule_prefer_newer(&mut all_rules, key, rule);
                         }
                     }
+                    // Offline there is no later update to restore the rules
+                    // of a source without a cached download.
+                    Err(e) if options.offline || fail_on_source_error => {
+                        return Err(e.context(format!(
+                            "Failed to process {source_name}, not updating the rules"
+                        )));
+                    }
                     Err(e) => {
                         eprintln!(
                             "{}: Failed to process {}: {}",
@@ -213,6 +538,10 @@ impl<'a> UpdateManager<'a> {
                         );
                     }
                 }
+            } else if fail_on_source_error {
+                return Err(anyhow::anyhow!(
+                    "Source {source_name} not found in index, not updating the rules"
+                ));
             } else {
                 eprintln!(
                     "{}: Source {} not found in index",
@@ -222,6 +551,27 @@ impl<'a> UpdateManager<'a> {
             }
         }
 
+        let mut local_paths = LocalRulesManager::new(self.path_provider).get_local_paths()?;
+        local_paths.extend(options.local.iter().cloned());
+        if !local_paths.is_empty() {
+            info_println!("\nProcessing local rules");
+            match self.process_local(&local_paths, &rule_conf, quiet) {
+                Ok(processed) => {
+                    info_println!(
+                        "  Loaded {} local rules",
+                        processed.rules.len().to_string().green()
+                    );
+                    disabled_rules.extend(processed.disabled);
+                    for (key, rule) in processed.rules {
+                        Self::insert_rule_prefer_newer(&mut all_rules, key, rule);
+                    }
+                }
+                Err(e) => {
+                    return Err(e.context("Failed to process local rules, not updating the rules"));
+                }
+            }
+        }
+
         let filtered_rule_count = Self::filter_rules(&mut all_rules, &rule_filters);
         if filtered_rule_count > 0 {
             info_println!(
@@ -230,129 +580,620 @@ impl<'a> UpdateManager<'a> {
             );
         }
 
+        let flowbit_rules = flowbits::resolve_flowbits(&mut all_rules, &disabled_rules);
+        if !flowbit_rules.is_empty() {
+            for key in &flowbit_rules {
+                debug!("Enabled rule {} for flowbit dependencies", key);
+            }
+            info_println!(
+                "\nEnabled {} rules for flowbit dependencies",
+                flowbit_rules.len().to_string().yellow()
+            );
+        }
+
         let all_dataset_files = Self::collect_dataset_files(&all_rules);
 
-        // Write merged rules and datasets to output files
-        self.write_rules(&all_rules)?;
-        self.write_dataset_files(&all_dataset_files)?;
+        // Write the rules and datasets to a staging directory and test them
+        // with Suricata before replacing the current rules. Suricata loads
+        // datasets relative to the rule file, so the test uses the staged
+        // datasets.
+        let staging_dir = self.get_staging_dir();
+        Self::remove_staging_dir(&staging_dir)?;
+        let tmp_path = staging_dir.join(DEFAULT_OUTPUT_FILE);
+        self.write_rules(&all_rules, &tmp_path)?;
+        Self::write_dataset_contents(&staging_dir, &all_dataset_files)?;
+
+        if options.no_test {
+            debug!("Skipping Suricata rule test");
+        } else if let Err(err) = self.test_rules(&tmp_path, options.suricata_conf.as_deref(), quiet)
+        {
+            let _ = fs::remove_dir_all(&staging_dir);
+            return Err(err.context("Rules failed Suricata test, keeping the existing rules"));
+        }
+
+        let output_path = self.get_output_path();
+        let rules_changed = Self::files_differ(&tmp_path, &output_path)?;
+        let report = RuleReport::new(&self.load_previous_rules(&output_path)?, &all_rules);
+        let datasets_changed = Self::write_dataset_contents(&self.rules_dir, &all_dataset_files)?;
+        fs::rename(&tmp_path, &output_path).with_context(|| {
+            format!(
+                "Failed to move {} to {}",
+                tmp_path.display(),
+                output_path.display()
+            )
+        })?;
+        Self::remove_staging_dir(&staging_dir)?;
+        let dataset_paths: HashSet<&PathBuf> = all_dataset_files.keys().collect();
+        self.cleanup_unreferenced_dataset_files(&dataset_paths)?;
 
         info_println!(
             "\n{}: Wrote {} rules and {} dataset files to {}",
             "Success".green().bold(),
             all_rules.len().to_string().green(),
             all_dataset_files.len().to_string().green(),
-            self.path_provider.rules_dir().display()
+            self.rules_dir.display()
         );
 
+        if !quiet {
+            report.print();
+        }
+        if let Some(path) = &options.report_json {
+            report.write_json(path)?;
+            info_println!("Wrote rule change report to {}", path.display());
+        }
+
+        if options.reload_command.is_some() || options.reload_socket.is_some() {
+            if rules_changed || datasets_changed {
+                Self::reload_suricata(options, quiet)
+                    .context("Rules were updated but reloading Suricata failed")?;
+            } else {
+                info_println!("\nNo changes to the rules, not reloading Suricata");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load the rule configuration files given in the options, or those
+    /// found in the configuration directory.
+    fn load_rule_conf(&self, options: &UpdateOptions) -> Result<RuleConf> {
+        let config_dir = self.path_provider.config_dir();
+        let conf_path = |path: &Option<PathBuf>, name: &str| {
+            path.clone().or_else(|| {
+                let path = config_dir.join(name);
+                path.exists().then_some(path)
+            })
+        };
+        RuleConf::load(
+            conf_path(&options.enable_conf, ENABLE_CONF).as_deref(),
+            conf_path(&options.disable_conf, DISABLE_CONF).as_deref(),
+            conf_path(&options.drop_conf, DROP_CONF).as_deref(),
+            conf_path(&options.modify_conf, MODIFY_CONF).as_deref(),
+        )
+    }
+
+    fn reload_suricata(options: &UpdateOptions, quiet: bool) -> Result<()> {
+        if let Some(command) = &options.reload_command {
+            if !quiet {
+                println!("\nReloading Suricata: {}", command.bright_black());
+            }
+            reload::run_reload_command(command)?;
+        }
+
+        if let Some(socket) = &options.reload_socket {
+            if !quiet {
+                println!(
+                    "\nReloading Suricata rules over {}",
+                    socket.display().to_string().bright_black()
+                );
+            }
+            let message = reload::reload_rules_socket(socket)?;
+            if !quiet {
+                println!("  Suricata: {}", message.green());
+            }
+        }
+
         Ok(())
     }
 
+    /// Load the rules from a previous output file, keyed by gid:sid.
+    fn load_previous_rules(&self, path: &Path) -> Result<HashMap<String, Rule>> {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(self
+            .parse_rules(DEFAULT_OUTPUT_FILE, &content)?
+            .into_iter()
+            .map(|rule| (format!("{}:{}", rule.gid, rule.sid), rule))
+            .collect())
+    }
+
+    /// Returns true if the content of the files differ, or the old file does
+    /// not exist.
+    fn files_differ(new_path: &Path, old_path: &Path) -> Result<bool> {
+        let old = match fs::read(old_path) {
+            Ok(old) => old,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", old_path.display()))
+            }
+        };
+        let new =
+            fs::read(new_path).with_context(|| format!("Failed to read {}", new_path.display()))?;
+        Ok(old != new)
+    }
+
     fn is_tty() -> bool {
         // Check if stdout is a TTY
         std::io::stdout().is_terminal()
     }
 
+    pub(crate) fn suricata_version(&self) -> &str {
+        &self.suricata_version
+    }
+
+    /// The resolved URL of an enabled source and its newest cached download,
+    /// if any.
+    pub(crate) fn cached_source(
+        &self,
+        source_info: &SourceInfo,
+        enabled_source: &EnabledSource,
+    ) -> Result<(String, Option<PathBuf>)> {
+        let request = self.resolve_source(source_info, enabled_source)?;
+        let cache_path = self.find_cached_source(&request.cache_key());
+        Ok((request.display_url, cache_path))
+    }
+
+    /// The hash the download of a source is cached by.
+    pub(crate) fn source_cache_key(
+        &self,
+        source_info: &SourceInfo,
+        enabled_source: &EnabledSource,
+    ) -> Result<String> {
+        Ok(self
+            .resolve_source(source_info, enabled_source)?
+            .cache_key())
+    }
+
+    fn resolve_source(
+        &self,
+        source_info: &SourceInfo,
+        enabled_source: &EnabledSource,
+    ) -> Result<SourceRequest> {
+        if enabled_source.url.is_none() && !crate::paths::has_scheme(&source_info.url) {
+            return Err(anyhow::anyhow!(
+                "Index URL {} of source {} is not allowed, a local source in an index must use a file:// URL",
+                source_info.url,
+                enabled_source.source
+            ));
+        }
+        let url_template = enabled_source.url.as_deref().unwrap_or(&source_info.url);
+        let url = self
+            .resolve_url(url_template, enabled_source.params.as_ref())
+            .with_context(|| {
+                format!("Failed to resolve URL for source {}", enabled_source.source)
+            })?;
+        let display_url =
+            self.resolve_url(url_template, enabled_source.redacted().params.as_ref())?;
+
+        if let Some(header) = &enabled_source.http_header {
+            Self::parse_http_header(header)?;
+        }
+
+        Ok(SourceRequest {
+            url,
+            display_url,
+            http_header: enabled_source.http_header.clone(),
+            checksum: enabled_source
+                .checksum
+                .or(source_info.checksum)
+                .unwrap_or(true),
+            public_key: enabled_source
+                .public_key
+                .clone()
+                .or_else(|| source_info.public_key.clone()),
+        })
+    }
+
+    /// Download the sources with a bounded pool of workers, returning the
+    /// location of the files of each source in the order given.
+    fn fetch_sources(
+        &self,
+        sources: &[(&str, &SourceRequest)],
+        force: bool,
+        offline: bool,
+        quiet: bool,
+    ) -> Vec<Result<SourceLocation>> {
+        let progress = DownloadProgress::new(quiet);
+        let next = AtomicUsize::new(0);
+        let workers = sources.len().min(DOWNLOAD_WORKERS);
+
+        let mut results: Vec<(usize, Result<SourceLocation>)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some((source_name, request)) = sources.get(index) else {
+                                break;
+                            };
+                            let result =
+                                self.fetch_source(source_name, request, force, offline, &progress);
+                            results.push((index, result));
+                        }
+                        results
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("download worker panicked"))
+                .collect()
+        });
+
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Download a source, or use the cached download when offline. Local
+    /// sources are read in place.
+    fn fetch_source(
+        &self,
+        source_name: &str,
+        request: &SourceRequest,
+        force: bool,
+        offline: bool,
+        progress: &DownloadProgress,
+    ) -> Result<SourceLocation> {
+        if let Some(path) = crate::paths::local_url_path(&request.url)? {
+            if let Some(public_key) = &request.public_key {
+                if path.is_dir() {
+                    return Err(anyhow::anyhow!(
+                        "Cannot verify the signature of {source_name}, a local directory"
+                    ));
+                }
+                crate::signature::verify_local_file(public_key, &path)
+                    .with_context(|| format!("Refusing local source {source_name}"))?;
+            }
+            let location = SourceLocation::local(&path)?;
+            progress.println(format!(
+                "  {}: Reading {}",
+                source_name.cyan(),
+                path.display().to_string().bright_black()
+            ));
+            return Ok(location);
+        }
+        if !offline {
+            return self
+                .download_source(source_name, request, force, progress)
+                .map(SourceLocation::cached);
+        }
+        let cache_path = self
+            .find_cached_source(&request.cache_key())
+            .ok_or_else(|| {
+                anyhow::anyhow!("No cached download of {source_name} found, cannot update offline")
+            })?;
+        Self::verify_cached_source(request, &cache_path)
+            .with_context(|| format!("Refusing cached download of {source_name}"))?;
+        progress.println(format!(
+            "  {}: Using cached file {}",
+            source_name.cyan(),
+            cache_path.display().to_string().bright_black()
+        ));
+        Ok(SourceLocation::cached(cache_path))
+    }
+
     fn process_source(
         &self,
         source_name: &str,
-        source_info: &SourceInfo,
+        request: &SourceRequest,
+        rule_conf: &RuleConf,
         force: bool,
+        offline: bool,
+        quiet: bool,
+    ) -> Result<ProcessedSource> {
+        let progress = DownloadProgress::new(quiet);
+        let location = self.fetch_source(source_name, request, force, offline, &progress)?;
+        self.process_source_files(source_name, request, &location, rule_conf, quiet)
+    }
+
+    fn process_source_files(
+        &self,
+        source_name: &str,
+        request: &SourceRequest,
+        location: &SourceLocation,
+        rule_conf: &RuleConf,
         quiet: bool,
     ) -> Result<ProcessedSource> {
-        // Download the source
-        let archive_path = self.download_source(source_name, source_info, force, quiet)?;
+        let filename = Self::source_rules_filename(source_name, &request.url);
+
+        // Read the dependency files, such as datasets, first so the rule
+        // files can be processed one at a time as they are read in a second
+        // pass over the files.
+        let mut dep_files: HashMap<PathBuf, Vec<u8>> = HashMap::new();
+        if location.has_dependencies() {
+            location.for_each_file(
+                &filename,
+                |name| !Self::is_rules_file(name),
+                |file| {
+                    if let Some(path) = Self::normalize_relative_path(Path::new(&file.filename)) {
+                        dep_files.insert(path, file.content);
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+
+        let mut processed = ProcessedSource::default();
+        let mut conf_stats = RuleConfStats::default();
+        location.for_each_file(
+            &filename,
+            |name| {
+                if !Self::is_rules_file(name) {
+                    return false;
+                }
+                if rule_conf.is_ignored(name) {
+                    debug!("Ignoring rule file {}", name);
+                    return false;
+                }
+                true
+            },
+            |file| {
+                self.process_rule_file(
+                    source_name,
+                    &file,
+                    &dep_files,
+                    rule_conf,
+                    &mut processed,
+                    &mut conf_stats,
+                )
+            },
+        )?;
 
-        // Extract files from archive
-        let source_files = self.extract_archive(&archive_path)?;
+        Self::print_processed_stats(&processed, &conf_stats, quiet);
+        Ok(processed)
+    }
+
+    /// Fall back to the last good download of a source that failed to
+    /// update, so a transient failure does not drop its rules from the
+    /// output. Downloads are only cached once verified, so the cached file
+    /// is the last one that downloaded successfully.
+    fn process_cached_source(
+        &self,
+        source_name: &str,
+        request: &SourceRequest,
+        rule_conf: &RuleConf,
+        err: anyhow::Error,
+        quiet: bool,
+    ) -> Result<ProcessedSource> {
+        if self.find_cached_source(&request.cache_key()).is_none() {
+            return Err(err);
+        }
+        eprintln!(
+            "{}: Failed to update {}: {:#}",
+            "Warning".yellow(),
+            source_name,
+            err
+        );
+        eprintln!(
+            "{}: Using the last cached download of {}",
+            "Warning".yellow(),
+            source_name
+        );
+        self.process_source(source_name, request, rule_conf, false, true, quiet)
+    }
 
-        // Partition source files into dependency files and rule files.
+    /// Process local rule files. Datasets referenced by the rules are loaded
+    /// relative to the rule file, like Suricata does.
+    fn process_local(
+        &self,
+        paths: &[PathBuf],
+        rule_conf: &RuleConf,
+        quiet: bool,
+    ) -> Result<ProcessedSource> {
         let mut dep_files: HashMap<PathBuf, Vec<u8>> = HashMap::new();
-        let mut rule_files: Vec<SourceFile> = Vec::new();
-        for file in source_files {
-            if Self::is_rules_file(&file.filename) {
-                rule_files.push(file);
-            } else if let Some(path) = Self::normalize_relative_path(Path::new(&file.filename)) {
-                dep_files.insert(path, file.content);
+        let mut processed = ProcessedSource::default();
+        let mut conf_stats = RuleConfStats::default();
+
+        for path in local::collect_rule_files(paths)? {
+            let content =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let group = Self::local_group(&path);
+            let prefix = Path::new(&group)
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .to_path_buf();
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+            for capture in
+                Self::dataset_load_regex().captures_iter(&String::from_utf8_lossy(&content))
+            {
+                let dataset_name = capture[1].trim().trim_matches('"').trim_matches('\'');
+                let dataset_path = Path::new(dataset_name);
+                if dataset_name.is_empty() || Self::is_absolute_or_rooted_path(dataset_path) {
+                    continue;
+                }
+                let Some(key) = Self::normalize_relative_path(&prefix.join(dataset_path)) else {
+                    continue;
+                };
+                if let Entry::Vacant(entry) = dep_files.entry(key) {
+                    if let Ok(dataset) = fs::read(dir.join(dataset_path)) {
+                        entry.insert(dataset);
+                    }
+                }
             }
+
+            let file = SourceFile {
+                filename: group,
+                content,
+            };
+            self.process_rule_file(
+                LOCAL_SOURCE,
+                &file,
+                &dep_files,
+                rule_conf,
+                &mut processed,
+                &mut conf_stats,
+            )?;
         }
 
-        // Parse rules and collect dataset files referenced by those rules.
-        let mut rules = HashMap::new();
+        Self::print_processed_stats(&processed, &conf_stats, quiet);
+        Ok(processed)
+    }
 
-        for file in rule_files {
-            let file_rules = self.parse_rules(&file.filename, &file.content)?;
-            for rule in file_rules {
-                if let Some((rewritten_rule, rule_datasets)) =
-                    Self::resolve_rule_datasets(source_name, &rule, &dep_files)?
-                {
-                    let mut rule = rule;
-                    rule.raw = rewritten_rule;
-                    rule.datasets = rule_datasets;
-                    let key = format!("{}:{}", rule.gid, rule.sid);
-                    Self::insert_rule_prefer_newer(&mut rules, key, rule);
-                } else {
-                    eprintln!(
-                        "{}: Missing dataset file for rule {}:{} (source: {})",
-                        "Warning".yellow(),
-                        rule.gid,
-                        rule.sid,
-                        source_name
-                    );
+    /// The rule group for a local rule file, its path without the root so
+    /// dataset paths relative to the file resolve like archive members.
+    fn local_group(path: &Path) -> String {
+        let relative: PathBuf = path
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .collect();
+        Self::path_to_rule_string(&relative)
+    }
+
+    /// Parse a rule file, apply the rule configuration and resolve the
+    /// datasets of its rules, adding the rules to the processed source.
+    fn process_rule_file(
+        &self,
+        source_name: &str,
+        file: &SourceFile,
+        dep_files: &HashMap<PathBuf, Vec<u8>>,
+        rule_conf: &RuleConf,
+        processed: &mut ProcessedSource,
+        conf_stats: &mut RuleConfStats,
+    ) -> Result<()> {
+        for mut rule in self.parse_rules(&file.filename, &file.content)? {
+            let conf_disabled = rule_conf.apply(&mut rule, conf_stats);
+            if let Some((rewritten_rule, rule_datasets)) =
+                Self::resolve_rule_datasets(source_name, &rule, dep_files)?
+            {
+                // Reparse only a rule whose dataset paths were rewritten.
+                if rewritten_rule != rule.raw {
+                    rule.set_raw(rewritten_rule)?;
                 }
+                rule.datasets = rule_datasets;
+                let key = format!("{}:{}", rule.gid, rule.sid);
+                if conf_disabled {
+                    processed.disabled.insert(key.clone());
+                }
+                Self::insert_rule_prefer_newer(&mut processed.rules, key, rule);
+            } else if rule.enabled {
+                eprintln!(
+                    "{}: Missing dataset file for rule {}:{} (source: {})",
+                    "Warning".yellow(),
+                    rule.gid,
+                    rule.sid,
+                    source_name
+                );
+            } else {
+                debug!(
+                    "Dropping disabled rule {}:{} with a missing dataset file (source: {})",
+                    rule.gid, rule.sid, source_name
+                );
             }
         }
+        Ok(())
+    }
 
-        if !quiet {
-            let dataset_count = rules
-                .values()
-                .flat_map(|r| &r.datasets)
-                .map(|d| &d.output_path)
-                .collect::<std::collections::HashSet<_>>()
-                .len();
+    fn print_processed_stats(processed: &ProcessedSource, conf_stats: &RuleConfStats, quiet: bool) {
+        if quiet {
+            return;
+        }
+
+        if !conf_stats.is_empty() {
             println!(
-                "  Found {} dataset files",
-                dataset_count.to_string().bright_black()
+                "  Enabled {}, disabled {}, dropped {} and modified {} rules",
+                conf_stats.enabled.to_string().bright_black(),
+                conf_stats.disabled.to_string().bright_black(),
+                conf_stats.dropped.to_string().bright_black(),
+                conf_stats.modified.to_string().bright_black()
             );
         }
 
-        Ok(ProcessedSource { rules })
+        let dataset_count = processed
+            .rules
+            .values()
+            .flat_map(|r| &r.datasets)
+            .map(|d| &d.output_path)
+            .collect::<std::collections::HashSet<_>>()
+            .len();
+        println!(
+            "  Found {} dataset files",
+            dataset_count.to_string().bright_black()
+        );
     }
 
     fn download_source(
         &self,
         source_name: &str,
-        source_info: &SourceInfo,
+        request: &SourceRequest,
         force: bool,
-        quiet: bool,
+        progress: &DownloadProgress,
     ) -> Result<PathBuf> {
-        // Resolve URL template
-        let url = self.resolve_url(&source_info.url);
-
-        // Generate cache filename based on URL hash
-        let url_hash = format!("{:x}", md5::compute(url.as_bytes()));
-        let cache_filename = format!("{url_hash}.tar.gz");
-        let cache_path = self.path_provider.cache_dir().join(&cache_filename);
+        let url = &request.display_url;
+
+        // Generate cache filename based on URL hash, the extension is the
+        // detected format of the download.
+        let url_hash = request.cache_key();
+        // A cached file that fails verification is downloaded again.
+        let cached =
+            self.find_cached_source(&url_hash).filter(
+                |cache_path| match Self::verify_cached_source(request, cache_path) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        debug!("Not using cached file of {}: {:#}", source_name, err);
+                        false
+                    }
+                },
+            );
 
         // Check if we have a recent cache (unless force is specified)
-        if !force && cache_path.exists() {
-            if let Ok(metadata) = fs::metadata(&cache_path) {
+        if let Some(cache_path) = cached.as_ref().filter(|_| !force) {
+            if let Ok(metadata) = fs::metadata(cache_path) {
                 if let Ok(modified) = metadata.modified() {
                     let age = Utc::now()
                         .signed_duration_since(DateTime::<Utc>::from(modified))
                         .num_seconds();
                     if age < CACHE_MIN_AGE_SECS {
-                        if !quiet {
-                            println!(
-                                "  Using cached file (age: {} seconds)",
-                                age.to_string().bright_black()
-                            );
-                        }
-                        return Ok(cache_path);
+                        progress.println(format!(
+                            "  {}: Using cached file (age: {} seconds)",
+                            source_name.cyan(),
+                            age.to_string().bright_black()
+                        ));
+                        return Ok(cache_path.clone());
                     }
                 }
             }
         }
 
+        let client = self.http.build_client()?;
+
+        // The published checksum is used to skip downloading an unchanged
+        // archive, and to verify the download.
+        let remote_md5 = if request.checksum {
+            self.fetch_remote_checksum(&client, source_name, request)
+        } else {
+            None
+        };
+
+        // If the remote checksum matches the cached file there is no need to
+        // download the archive again.
+        if let (Some(remote_md5), Some(cache_path)) =
+            (&remote_md5, cached.as_ref().filter(|_| !force))
+        {
+            if &Self::file_md5(cache_path)? == remote_md5 {
+                progress.println(format!(
+                    "  {}: Remote checksum has not changed, using cached file",
+                    source_name.cyan()
+                ));
+                crate::paths::touch(cache_path)?;
+                return Ok(cache_path.clone());
+            }
+        }
+
         // Ensure cache directory exists
         crate::paths::ensure_dir_exists(&self.path_provider.cache_dir()).with_context(|| {
             format!(
@@ -362,23 +1203,39 @@ impl<'a> UpdateManager<'a> {
         })?;
 
         // Download the file
-        if force && cache_path.exists() && !quiet {
-            println!("  Forcing download (ignoring cache)");
-        }
-        if !quiet {
-            println!("  Downloading: {}", url.bright_black());
+        if force && cached.is_some() {
+            progress.println(format!(
+                "  {}: Forcing download (ignoring cache)",
+                source_name.cyan()
+            ));
         }
-
-        let user_agent = UserAgent::new().to_string();
-        debug!("Using User-Agent: {}", user_agent);
-        let client = reqwest::blocking::Client::builder()
-            .user_agent(user_agent)
-            .build()?;
-        let mut response = client
-            .get(&url)
-            .send()
+        progress.println(format!(
+            "  {}: Downloading {}",
+            source_name.cyan(),
+            url.bright_black()
+        ));
+
+        // Make the request conditional on the cached file having changed.
+        let validators = match cached.as_ref().filter(|_| !force) {
+            Some(cache_path) => CacheValidators::load(cache_path),
+            None => CacheValidators::default(),
+        };
+        let mut response = self
+            .http
+            .send(validators.apply(Self::build_request(&client, &request.url, request)?))
             .with_context(|| format!("Failed to download {url}"))?;
 
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cache_path) = cached {
+                progress.println(format!(
+                    "  {}: Source has not been modified, using cached file",
+                    source_name.cyan()
+                ));
+                crate::paths::touch(&cache_path)?;
+                return Ok(cache_path);
+            }
+        }
+
         if !response.status().is_success() {
             return Err(anyhow::anyhow!(
                 "Failed to download {}: HTTP {}",
@@ -389,118 +1246,373 @@ impl<'a> UpdateManager<'a> {
 
         // Get content length for progress bar
         let content_length = response.content_length();
-
-        // Create progress bar if we have a TTY and know the content length (and not quiet)
-        let progress_bar = if Self::is_tty() && !quiet {
-            content_length.map(|content_length| {
-                let progress_bar = ProgressBar::new(content_length);
-                progress_bar.set_style(
-                    ProgressStyle::default_bar()
-                        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-                        .unwrap()
-                        .progress_chars("#>-")
-                );
-                progress_bar
-            })
-        } else {
-            None
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .cloned();
+        let validators = CacheValidators::from_headers(response.headers());
+
+        // Show a progress bar if we have a TTY and know the content length
+        let progress_bar =
+            content_length.and_then(|content_length| progress.add_bar(source_name, content_length));
+
+        // Stream the download to a temporary file in the cache directory,
+        // it only replaces the cached file once complete and verified.
+        let tmp_path = self
+            .path_provider
+            .cache_dir()
+            .join(format!("{url_hash}.download"));
+        let result = Self::write_download(
+            source_name,
+            request,
+            &mut response,
+            &tmp_path,
+            content_type.as_ref().and_then(|value| value.to_str().ok()),
+            remote_md5.as_deref(),
+            progress_bar.as_ref(),
+        )
+        .and_then(|download| {
+            let signature = self.verify_signature(&client, source_name, request, &tmp_path)?;
+            Ok((download, signature))
+        });
+        if let Some(pb) = progress_bar {
+            pb.finish_and_clear();
+        }
+        let ((format, size), signature) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                let _ = fs::remove_file(&tmp_path);
+                return Err(err);
+            }
         };
 
-        // Download with progress
-        let mut downloaded = Vec::new();
+        debug!("Detected {} format for {}", format.extension(), url);
+        let cache_path = self
+            .path_provider
+            .cache_dir()
+            .join(format!("{url_hash}.{}", format.extension()));
+
+        // Remove a cached file of a different format so it is not found
+        // instead of this one.
+        if let Some(previous) = cached.filter(|previous| previous != &cache_path) {
+            let _ = fs::remove_file(&previous);
+            let _ = fs::remove_file(crate::signature::signature_path(&previous));
+            CacheValidators::remove(&previous)?;
+        }
+
+        fs::rename(&tmp_path, &cache_path).with_context(|| {
+            format!(
+                "Failed to move {} to {}",
+                tmp_path.display(),
+                cache_path.display()
+            )
+        })?;
+
+        validators.save(&cache_path)?;
+
+        // Keep the signature next to the cached file, so the file can be
+        // verified again when it is used from the cache.
+        if let Some(signature) = signature {
+            let signature_path = crate::signature::signature_path(&cache_path);
+            fs::write(&signature_path, signature)
+                .with_context(|| format!("Failed to write {}", signature_path.display()))?;
+        }
+
+        progress.println(format!(
+            "  {}: Downloaded {} bytes",
+            source_name.cyan(),
+            size.to_string().green()
+        ));
+        Ok(cache_path)
+    }
+
+    /// Write a download to a file as it is received, verifying the checksum
+    /// and detecting the format on the way. Returns the format and size of
+    /// the download.
+    fn write_download(
+        source_name: &str,
+        request: &SourceRequest,
+        response: &mut Response,
+        path: &Path,
+        content_type: Option<&str>,
+        remote_md5: Option<&str>,
+        progress_bar: Option<&ProgressBar>,
+    ) -> Result<(ArchiveFormat, u64)> {
+        let url = &request.display_url;
+        let file = fs::File::create(path).with_context(|| {
+            format!(
+                "Failed to create cache file {}: permission denied",
+                path.display()
+            )
+        })?;
+        let mut writer = BufWriter::new(file);
+        let mut md5 = md5::Context::new();
+        // The start of the download is kept to detect its format.
+        let mut head = Vec::with_capacity(archive::DETECT_LEN);
+        let mut size = 0;
         let mut buffer = [0; 8192];
 
         loop {
-            let bytes_read = response.read(&mut buffer)?;
+            let bytes_read = response
+                .read(&mut buffer)
+                .with_context(|| format!("Failed to download {url}"))?;
             if bytes_read == 0 {
                 break;
             }
-            downloaded.extend_from_slice(&buffer[..bytes_read]);
-
-            if let Some(ref pb) = progress_bar {
+            let chunk = &buffer[..bytes_read];
+            writer
+                .write_all(chunk)
+                .with_context(|| format!("Failed to write to cache file {}", path.display()))?;
+            md5.consume(chunk);
+            let head_len = (archive::DETECT_LEN - head.len()).min(chunk.len());
+            head.extend_from_slice(&chunk[..head_len]);
+            size += bytes_read as u64;
+
+            if let Some(pb) = progress_bar {
                 pb.inc(bytes_read as u64);
             }
         }
+        writer
+            .flush()
+            .and_then(|()| writer.get_ref().sync_all())
+            .with_context(|| format!("Failed to write to cache file {}", path.display()))?;
+
+        if let Some(remote_md5) = remote_md5 {
+            Self::verify_checksum(&format!("{:x}", md5.compute()), remote_md5)
+                .with_context(|| format!("Refusing corrupt download of {source_name}"))?;
+            debug!("Verified checksum of {}", url);
+        }
 
-        if let Some(pb) = progress_bar {
-            pb.finish_and_clear();
+        Ok((
+            ArchiveFormat::detect(&request.url, content_type, &head),
+            size,
+        ))
+    }
+
+    /// Verify a download against the signature published next to it,
+    /// returning the signature. None if the source has no public key.
+    fn verify_signature(
+        &self,
+        client: &reqwest::blocking::Client,
+        source_name: &str,
+        request: &SourceRequest,
+        path: &Path,
+    ) -> Result<Option<String>> {
+        let Some(public_key) = &request.public_key else {
+            return Ok(None);
+        };
+        let url = crate::signature::signature_url(&request.display_url);
+        let response = self
+            .http
+            .send(Self::build_request(
+                client,
+                &crate::signature::signature_url(&request.url),
+                request,
+            )?)
+            .with_context(|| format!("Failed to download {url}"))?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Refusing unsigned download of {}, no signature found at {}: HTTP {}",
+                source_name,
+                url,
+                response.status()
+            ));
         }
+        let signature = response
+            .text()
+            .with_context(|| format!("Failed to download {url}"))?;
+        crate::signature::verify_file(public_key, path, &signature)
+            .with_context(|| format!("Refusing download of {source_name}"))?;
+        debug!("Verified signature of {}", request.display_url);
+        Ok(Some(signature))
+    }
 
-        // Write to cache file
-        let mut file = fs::File::create(&cache_path).with_context(|| {
-            format!(
-                "Failed to create cache file {}: permission denied",
-                cache_path.display()
-            )
-        })?;
-        file.write_all(&downloaded).with_context(|| {
-            format!(
-                "Failed to write to cache file {}: permission denied",
-                cache_path.display()
-            )
-        })?;
+    /// Verify a cached download against the signature saved next to it, if
+    /// the source has a public key.
+    fn verify_cached_source(request: &SourceRequest, cache_path: &Path) -> Result<()> {
+        match &request.public_key {
+            Some(public_key) => crate::signature::verify_local_file(public_key, cache_path),
+            None => Ok(()),
+        }
+    }
 
-        if !quiet {
-            println!(
-                "  Downloaded {} bytes",
-                downloaded.len().to_string().green()
+    fn url_hash(url: &str) -> String {
+        format!("{:x}", md5::compute(url.as_bytes()))
+    }
+
+    /// The cached download for a URL hash, in any of the supported formats.
+    /// If there is more than one, the newest is used.
+    fn find_cached_source(&self, url_hash: &str) -> Option<PathBuf> {
+        ArchiveFormat::ALL
+            .iter()
+            .map(|format| {
+                self.path_provider
+                    .cache_dir()
+                    .join(format!("{url_hash}.{}", format.extension()))
+            })
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((modified, path))
+            })
+            .max_by_key(|(modified, _)| *modified)
+            .map(|(_, path)| path)
+    }
+
+    fn build_request(
+        client: &reqwest::blocking::Client,
+        url: &str,
+        request: &SourceRequest,
+    ) -> Result<reqwest::blocking::RequestBuilder> {
+        let mut builder = client.get(url);
+        if let Some(header) = &request.http_header {
+            let (name, value) = Self::parse_http_header(header)?;
+            builder = builder.header(name, value);
+        }
+        Ok(builder)
+    }
+
+    pub(crate) fn parse_http_header(header: &str) -> Result<(HeaderName, HeaderValue)> {
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Invalid http-header, expected \"Name: value\""))?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .with_context(|| format!("Invalid http-header name: {}", name.trim()))?;
+        let value = HeaderValue::from_str(value.trim())
+            .with_context(|| format!("Invalid http-header value for {name}"))?;
+        Ok((name, value))
+    }
+
+    /// Fetch the published checksum of a source. A source without a
+    /// checksum file is not an error, but a failed request is reported as
+    /// the download goes ahead without checksum verification.
+    fn fetch_remote_checksum(
+        &self,
+        client: &reqwest::blocking::Client,
+        source_name: &str,
+        request: &SourceRequest,
+    ) -> Option<String> {
+        let url = format!("{}.md5", request.display_url);
+        let builder = Self::build_request(client, &format!("{}.md5", request.url), request).ok()?;
+        let response = match self.http.send(builder) {
+            Ok(response) => response,
+            Err(err) => {
+                eprintln!(
+                    "{}: Failed to fetch the checksum of {}, not verifying it: {}",
+                    "Warning".yellow(),
+                    source_name,
+                    err
+                );
+                return None;
+            }
+        };
+        if response.status().is_server_error() {
+            eprintln!(
+                "{}: Failed to fetch the checksum of {}, not verifying it: HTTP {}",
+                "Warning".yellow(),
+                source_name,
+                response.status()
+            );
+            return None;
+        }
+        if !response.status().is_success() {
+            debug!(
+                "Failed to fetch checksum {}: HTTP {}",
+                url,
+                response.status()
             );
+            return None;
+        }
+        Self::parse_md5_checksum(&response.text().ok()?)
+    }
+
+    fn parse_md5_checksum(content: &str) -> Option<String> {
+        let checksum = content.split_whitespace().next()?.to_ascii_lowercase();
+        if checksum.len() == 32 && checksum.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            Some(checksum)
+        } else {
+            None
         }
-        Ok(cache_path)
     }
 
-    fn resolve_url(&self, url_template: &str) -> String {
-        // Replace %(__version__)s with suricata version
-        url_template.replace("%(__version__)s", &self.suricata_version)
+    fn verify_checksum(actual: &str, expected_md5: &str) -> Result<()> {
+        if actual != expected_md5 {
+            return Err(anyhow::anyhow!(
+                "Checksum mismatch, expected {} but downloaded file has {}",
+                expected_md5,
+                actual
+            ));
+        }
+        Ok(())
     }
 
-    fn extract_archive(&self, archive_path: &Path) -> Result<Vec<SourceFile>> {
-        let mut files = Vec::new();
-        let file = fs::File::open(archive_path)?;
-
-        if archive_path.to_string_lossy().ends_with(".tar.gz") {
-            // Handle tar.gz files
-            let tar = GzDecoder::new(file);
-            let mut archive = Archive::new(tar);
-
-            for entry in archive.entries()? {
-                let mut entry = entry?;
-                let path = entry.path()?;
-
-                // Skip directories
-                if entry.header().entry_type().is_dir() {
-                    continue;
-                }
+    fn file_md5(path: &Path) -> Result<String> {
+        let mut file =
+            fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut context = md5::Context::new();
+        let mut buffer = [0; 8192];
+        loop {
+            let bytes_read = file.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            context.consume(&buffer[..bytes_read]);
+        }
+        Ok(format!("{:x}", context.compute()))
+    }
 
-                let filename = path.to_string_lossy().to_string();
-                let mut content = Vec::new();
-                entry.read_to_end(&mut content)?;
+    fn resolve_url(
+        &self,
+        url_template: &str,
+        params: Option<&HashMap<String, serde_yaml::Value>>,
+    ) -> Result<String> {
+        // Replace %(__version__)s with suricata version
+        let mut url = url_template.replace("%(__version__)s", &self.suricata_version);
 
-                files.push(SourceFile { filename, content });
+        // Replace %(name)s with the value of each configured parameter
+        if let Some(params) = params {
+            for (name, value) in params {
+                url = url.replace(&format!("%({name})s"), &Self::param_value(value));
             }
-        } else if archive_path.to_string_lossy().ends_with(".zip") {
-            // Handle zip files
-            let mut archive = ZipArchive::new(file)?;
+        }
 
-            for i in 0..archive.len() {
-                let mut file = archive.by_index(i)?;
+        if let Some(capture) = Self::url_param_regex().captures(&url) {
+            return Err(anyhow::anyhow!(
+                "URL requires parameter {}, set it with enable-ruleset --param {}=VALUE",
+                &capture[1],
+                &capture[1]
+            ));
+        }
 
-                // Skip directories
-                if file.is_dir() {
-                    continue;
-                }
+        Ok(url)
+    }
 
-                let filename = file.name().to_string();
-                let mut content = Vec::new();
-                file.read_to_end(&mut content)?;
+    fn url_param_regex() -> &'static Regex {
+        static URL_PARAM_RE: OnceLock<Regex> = OnceLock::new();
+        URL_PARAM_RE.get_or_init(|| Regex::new(r"%\(([^)]+)\)s").expect("valid regex"))
+    }
 
-                files.push(SourceFile { filename, content });
-            }
-        } else {
-            return Err(anyhow::anyhow!("Unsupported archive format"));
+    fn param_value(value: &serde_yaml::Value) -> String {
+        match value {
+            serde_yaml::Value::String(value) => value.clone(),
+            serde_yaml::Value::Number(value) => value.to_string(),
+            serde_yaml::Value::Bool(value) => value.to_string(),
+            other => serde_yaml::to_string(other)
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default(),
         }
+    }
 
-        Ok(files)
+    /// The filename for a source served as a single rule file, the last
+    /// component of the URL path without any compression extension.
+    fn source_rules_filename(source_name: &str, url: &str) -> String {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let name = path.rsplit('/').next().unwrap_or_default();
+        let name = name.strip_suffix(".gz").unwrap_or(name);
+        if Self::is_rules_file(name) {
+            name.to_string()
+        } else {
+            format!("{}.rules", RulesetManager::safe_filename(source_name))
+        }
     }
 
     fn is_rules_file(filename: &str) -> bool {
@@ -615,7 +1727,7 @@ impl<'a> UpdateManager<'a> {
 
     fn collect_dataset_files(rules: &HashMap<String, Rule>) -> HashMap<PathBuf, Vec<u8>> {
         let mut dataset_files: HashMap<PathBuf, Vec<u8>> = HashMap::new();
-        for rule in rules.values() {
+        for rule in rules.values().filter(|rule| rule.enabled) {
             for dataset in &rule.datasets {
                 match dataset_files.entry(dataset.output_path.clone()) {
                     Entry::Vacant(entry) => {
@@ -636,15 +1748,15 @@ impl<'a> UpdateManager<'a> {
         dataset_files
     }
 
+    /// Rewrite the dataset load paths of a rule to the datasets written with
+    /// the rules. Returns None if a dataset can't be resolved, also for a
+    /// disabled rule, as it would load a dataset that is never written if it
+    /// were enabled for a flowbit dependency.
     fn resolve_rule_datasets(
         source_name: &str,
         rule: &Rule,
         dep_files: &HashMap<PathBuf, Vec<u8>>,
     ) -> Result<Option<(String, Vec<ResolvedDataset>)>> {
-        if !rule.enabled {
-            return Ok(Some((rule.raw.clone(), Vec::new())));
-        }
-
         let mut datasets: Vec<ResolvedDataset> = Vec::new();
         let mut rewritten_rule = String::with_capacity(rule.raw.len() + 32);
         let mut cursor = 0;
@@ -717,60 +1829,51 @@ impl<'a> UpdateManager<'a> {
     fn parse_rules(&self, group: &str, content: &[u8]) -> Result<Vec<Rule>> {
         let content_str = String::from_utf8_lossy(content);
         let mut rules = Vec::new();
+        let mut lines = content_str.lines().enumerate();
+
+        while let Some((index, line)) = lines.next() {
+            let line_number = index + 1;
+            let mut text = line.trim().to_string();
+            let commented = text.starts_with('#');
+
+            // Join rules continued over multiple lines with a trailing backslash.
+            while text.ends_with('\\') {
+                text.pop();
+                text.truncate(text.trim_end().len());
+                let Some((_, next)) = lines.next() else {
+                    break;
+                };
+                let next = next.trim();
+                let next = if commented {
+                    next.trim_start_matches('#')
+                } else {
+                    next
+                };
+                text.push(' ');
+                text.push_str(next.trim());
+            }
 
-        // Simple rule parser - matches basic rule structure
-        let rule_regex = Regex::new(r"^(#?\s*)?(alert|drop|pass|reject)\s+.*?sid:\s*(\d+).*?;")?;
-        let sid_regex = Regex::new(r"sid:\s*(\d+)")?;
-        let gid_regex = Regex::new(r"gid:\s*(\d+)")?;
-        let rev_regex = Regex::new(r"rev:\s*(\d+)")?;
-        let msg_regex = Regex::new(r#"msg:\s*"([^"]+)""#)?;
-
-        for line in content_str.lines() {
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with('#') && !trimmed.contains("sid:") {
+            let rule_text = text.trim_start_matches(|c: char| c == '#' || c.is_whitespace());
+            if rule_text.is_empty() || (commented && !parser::starts_with_action(rule_text)) {
                 continue;
             }
 
-            if rule_regex.is_match(trimmed) {
-                let enabled = !trimmed.starts_with('#');
-                let raw = trimmed.to_string();
-
-                // Extract rule components
-                let sid = sid_regex
-                    .captures(trimmed)
-                    .and_then(|c| c.get(1))
-                    .and_then(|m| m.as_str().parse::<u32>().ok())
-                    .unwrap_or(0);
-
-                let gid = gid_regex
-                    .captures(trimmed)
-                    .and_then(|c| c.get(1))
-                    .and_then(|m| m.as_str().parse::<u32>().ok())
-                    .unwrap_or(1);
-
-                let rev = rev_regex
-                    .captures(trimmed)
-                    .and_then(|c| c.get(1))
-                    .and_then(|m| m.as_str().parse::<u32>().ok())
-                    .unwrap_or(1);
-
-                let msg = msg_regex
-                    .captures(trimmed)
-                    .and_then(|c| c.get(1))
-                    .map(|m| m.as_str().to_string())
-                    .unwrap_or_default();
-
-                if sid > 0 {
-                    rules.push(Rule {
-                        raw,
-                        enabled,
-                        sid,
-                        gid,
-                        rev,
-                        group: group.to_string(),
-                        datasets: Vec::new(),
-                        msg,
-                    });
+            match Rule::parse(rule_text, !commented, group) {
+                Ok(rule) => rules.push(rule),
+                Err(err) if commented => {
+                    debug!(
+                        "Ignoring commented rule at {}:{}: {}",
+                        group, line_number, err
+                    );
+                }
+                Err(err) => {
+                    eprintln!(
+                        "{}: Failed to parse rule at {}:{}: {}",
+                        "Warning".yellow(),
+                        group,
+                        line_number,
+                        err
+                    );
                 }
             }
         }
@@ -778,9 +1881,7 @@ impl<'a> UpdateManager<'a> {
         Ok(rules)
     }
 
-    fn write_rules(&self, rules: &HashMap<String, Rule>) -> Result<()> {
-        let output_path = self.get_output_path();
-
+    fn write_rules(&self, rules: &HashMap<String, Rule>, output_path: &Path) -> Result<()> {
         // Ensure output directory exists
         if let Some(parent) = output_path.parent() {
             crate::paths::ensure_dir_exists(parent).with_context(|| {
@@ -791,12 +1892,14 @@ impl<'a> UpdateManager<'a> {
             })?;
         }
 
-        // Sort rules by gid:sid for consistent output
+        // Sort rules by gid:sid for consistent output. Disabled rules are
+        // written commented out so the next update can report rules that
+        // were enabled.
         let mut sorted_rules: Vec<_> = rules.values().collect();
         sorted_rules.sort_by_key(|r| (r.gid, r.sid));
 
         // Write rules to file
-        let mut file = fs::File::create(&output_path).with_context(|| {
+        let mut file = fs::File::create(output_path).with_context(|| {
             format!(
                 "Failed to create output file {}: permission denied",
                 output_path.display()
@@ -805,15 +1908,121 @@ impl<'a> UpdateManager<'a> {
         for rule in sorted_rules {
             if rule.enabled {
                 writeln!(file, "{}", rule.raw)?;
+            } else {
+                writeln!(file, "# {}", rule.raw)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Test a rule file by running Suricata in test mode. The test is skipped
+    /// with a warning if the default Suricata or configuration can't be
+    /// found, but a Suricata or configuration that was given must exist.
+    fn test_rules(
+        &self,
+        rules_path: &Path,
+        suricata_conf: Option<&Path>,
+        quiet: bool,
+    ) -> Result<()> {
+        if let Some(conf) = suricata_conf.filter(|conf| !conf.exists()) {
+            return Err(anyhow::anyhow!(
+                "Suricata configuration {} not found, use --no-test to skip testing the rules",
+                conf.display()
+            ));
+        }
+        let conf = suricata_conf
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.path_provider.config_dir().join(SURICATA_CONF));
+        if !conf.exists() {
+            eprintln!(
+                "{}: Suricata configuration {} not found, not testing rules",
+                "Warning".yellow(),
+                conf.display()
+            );
+            return Ok(());
+        }
+
+        let log_dir =
+            std::env::temp_dir().join(format!("suricasta-rules-test-{}", std::process::id()));
+        crate::paths::ensure_dir_exists(&log_dir)?;
+
+        if !quiet {
+            println!(
+                "\nTesting rules with {}",
+                self.suricata.display().to_string().cyan()
+            );
+        }
+
+        let result = std::process::Command::new(&self.suricata)
+            .arg("-T")
+            .arg("-S")
+            .arg(rules_path)
+            .arg("-c")
+            .arg(&conf)
+            .arg("-l")
+            .arg(&log_dir)
+            .output();
+        let _ = fs::remove_dir_all(&log_dir);
+
+        let output = match result {
+            Ok(output) => output,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && self.suricata_configured => {
+                return Err(anyhow::anyhow!(
+                    "Suricata not found at {}, use --no-test to skip testing the rules",
+                    self.suricata.display()
+                ));
             }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!(
+                    "{}: Suricata not found at {}, not testing rules",
+                    "Warning".yellow(),
+                    self.suricata.display()
+                );
+                return Ok(());
+            }
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to run {}", self.suricata.display()));
+            }
+        };
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let log = if stderr.trim().is_empty() {
+                stdout
+            } else {
+                stderr
+            };
+            return Err(anyhow::anyhow!(
+                "Suricata test failed ({}):\n{}",
+                output.status,
+                log.trim()
+            ));
         }
 
+        if !quiet {
+            println!("  Rules passed Suricata test");
+        }
         Ok(())
     }
 
-    fn write_dataset_files(&self, dataset_files: &HashMap<PathBuf, Vec<u8>>) -> Result<()> {
+    /// Write the dataset files to a directory, returning true if any file
+    /// was created or its content changed.
+    fn write_dataset_contents(
+        dir: &Path,
+        dataset_files: &HashMap<PathBuf, Vec<u8>>,
+    ) -> Result<bool> {
+        let mut changed = false;
+
         for (relative_path, content) in dataset_files {
-            let path = self.path_provider.rules_dir().join(relative_path);
+            let path = dir.join(relative_path);
+
+            if fs::read(&path).is_ok_and(|existing| &existing == content) {
+                continue;
+            }
+            changed = true;
 
             if let Some(parent) = path.parent() {
                 crate::paths::ensure_dir_exists(parent).with_context(|| {
@@ -832,17 +2041,11 @@ impl<'a> UpdateManager<'a> {
             })?;
         }
 
-        let dataset_paths: std::collections::HashSet<&PathBuf> = dataset_files.keys().collect();
-        self.cleanup_unreferenced_dataset_files(&dataset_paths)?;
-
-        Ok(())
+        Ok(changed)
     }
 
-    fn cleanup_unreferenced_dataset_files(
-        &self,
-        dataset_paths: &std::collections::HashSet<&PathBuf>,
-    ) -> Result<()> {
-        let rules_dir = self.path_provider.rules_dir();
+    fn cleanup_unreferenced_dataset_files(&self, dataset_paths: &HashSet<&PathBuf>) -> Result<()> {
+        let rules_dir = self.rules_dir.clone();
         let datasets_dir = rules_dir.join(DATASETS_DIR);
         let legacy_datasets_dir = datasets_dir.join(LEGACY_MANAGED_DATASETS_DIR);
 
@@ -914,73 +2117,623 @@ impl<'a> UpdateManager<'a> {
                     format!("Failed to inspect dataset entry {}", path.display())
                 })?;
 
-                if file_type.is_dir() {
-                    stack.push(path);
-                    continue;
-                }
+                if file_type.is_dir() {
+                    stack.push(path);
+                    continue;
+                }
+
+                if !file_type.is_file() {
+                    continue;
+                }
+
+                let Ok(relative_path) = path.strip_prefix(&rules_dir).map(Path::to_path_buf) else {
+                    continue;
+                };
+
+                if !dataset_paths.contains(&relative_path)
+                    && Self::should_cleanup_dataset_path(&relative_path)
+                {
+                    fs::remove_file(&path).with_context(|| {
+                        format!("Failed to remove unreferenced dataset {}", path.display())
+                    })?;
+                }
+            }
+        }
+
+        dirs.sort_by_key(|dir| Reverse(dir.components().count()));
+        for dir in dirs {
+            let mut entries = fs::read_dir(&dir)
+                .with_context(|| format!("Failed to read dataset directory {}", dir.display()))?;
+            if entries.next().is_none() {
+                fs::remove_dir(&dir).with_context(|| {
+                    format!("Failed to remove empty dataset directory {}", dir.display())
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_output_path(&self) -> PathBuf {
+        self.rules_dir.join(DEFAULT_OUTPUT_FILE)
+    }
+
+    /// Directory the rules and datasets are written to until they have been
+    /// tested.
+    fn get_staging_dir(&self) -> PathBuf {
+        self.rules_dir.join(STAGING_DIR)
+    }
+
+    fn remove_staging_dir(dir: &Path) -> Result<()> {
+        match fs::remove_dir_all(dir) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove {}", dir.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResolvedDataset, Rule, RuleFilters, UpdateManager, UpdateOptions};
+    use crate::archive::SourceLocation;
+    use crate::matchers::RuleConf;
+    use crate::paths::PathProvider;
+    use crate::report::{RuleChange, RuleReport};
+    use crate::rulesets::EnabledSource;
+    use crate::sources::{SourceIndex, SourceInfo};
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct TestPaths {
+        root: PathBuf,
+    }
+
+    impl PathProvider for TestPaths {
+        fn sources_dir(&self) -> PathBuf {
+            self.root.join("sources")
+        }
+
+        fn cache_dir(&self) -> PathBuf {
+            self.root.join("cache")
+        }
+
+        fn rules_dir(&self) -> PathBuf {
+            self.root.join("rules")
+        }
+
+        fn config_dir(&self) -> PathBuf {
+            self.root.join("config")
+        }
+    }
+
+    fn test_source_info(url: &str) -> SourceInfo {
+        SourceInfo {
+            vendor: "Test".to_string(),
+            summary: "Test source".to_string(),
+            url: url.to_string(),
+            description: None,
+            license: None,
+            homepage: None,
+            min_version: None,
+            checksum: None,
+            parameters: None,
+            replaces: None,
+            deprecated: None,
+            obsolete: None,
+            public_key: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_source_uses_enabled_source_overrides() {
+        let paths = TestPaths {
+            root: PathBuf::from("unused"),
+        };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.3"));
+        let source_info = test_source_info("https://example.com/%(__version__)s/index.tar.gz");
+
+        let mut enabled_source = EnabledSource::new("test/source".to_string());
+        let request = manager
+            .resolve_source(&source_info, &enabled_source)
+            .unwrap();
+        assert_eq!(request.url, "https://example.com/7.0.3/index.tar.gz");
+        assert!(request.http_header.is_none());
+        assert!(request.checksum);
+        assert!(request.public_key.is_none());
+
+        // The public key of a signed index applies unless the source has one.
+        let mut source_info = source_info;
+        source_info.public_key = Some("index-key".to_string());
+        let request = manager
+            .resolve_source(&source_info, &enabled_source)
+            .unwrap();
+        assert_eq!(request.public_key.as_deref(), Some("index-key"));
+        enabled_source.public_key = Some("source-key".to_string());
+        let request = manager
+            .resolve_source(&source_info, &enabled_source)
+            .unwrap();
+        assert_eq!(request.public_key.as_deref(), Some("source-key"));
+
+        enabled_source.url =
+            Some("https://mirror.example.com/%(__version__)s/%(code)s.tar.gz".to_string());
+        enabled_source.params = Some(HashMap::from([(
+            "code".to_string(),
+            serde_yaml::Value::String("abc123".to_string()),
+        )]));
+        enabled_source.http_header = Some("Authorization: Bearer token".to_string());
+        enabled_source.checksum = Some(false);
+
+        let request = manager
+            .resolve_source(&source_info, &enabled_source)
+            .unwrap();
+        assert_eq!(
+            request.url,
+            "https://mirror.example.com/7.0.3/abc123.tar.gz"
+        );
+        assert_eq!(
+            request.http_header.as_deref(),
+            Some("Authorization: Bearer token")
+        );
+        assert!(!request.checksum);
+    }
+
+    #[test]
+    fn test_resolve_source_requires_all_parameters() {
+        let paths = TestPaths {
+            root: PathBuf::from("unused"),
+        };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.3"));
+        let source_info = test_source_info(
+            "https://rules.example.com/%(secret-code)s/suricata-%(__version__)s/etpro.rules.tar.gz",
+        );
+        let mut enabled_source = EnabledSource::new("et/pro".to_string());
+
+        let err = manager
+            .resolve_source(&source_info, &enabled_source)
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("requires parameter secret-code"));
+
+        enabled_source.params = Some(HashMap::from([(
+            "secret-code".to_string(),
+            serde_yaml::Value::String("0123abcd".to_string()),
+        )]));
+        let request = manager
+            .resolve_source(&source_info, &enabled_source)
+            .unwrap();
+        assert_eq!(
+            request.url,
+            "https://rules.example.com/0123abcd/suricata-7.0.3/etpro.rules.tar.gz"
+        );
+        // The secret code is not shown in the output.
+        assert_eq!(
+            request.display_url,
+            "https://rules.example.com/***/suricata-7.0.3/etpro.rules.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_resolve_source_requires_file_url_from_index() {
+        let paths = TestPaths {
+            root: PathBuf::from("unused"),
+        };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.3"));
+        let mut enabled_source = EnabledSource::new("test/source".to_string());
+
+        // A plain path from an index is refused, a file:// URL is not.
+        let source_info = test_source_info("/etc/suricata");
+        assert!(manager
+            .resolve_source(&source_info, &enabled_source)
+            .is_err());
+        let source_info = test_source_info("file:///etc/suricata");
+        assert!(manager
+            .resolve_source(&source_info, &enabled_source)
+            .is_ok());
+
+        // A plain path from the local configuration is accepted.
+        let source_info = test_source_info("/etc/suricata");
+        enabled_source.url = Some("/var/lib/rules".to_string());
+        let request = manager
+            .resolve_source(&source_info, &enabled_source)
+            .unwrap();
+        assert_eq!(request.url, "/var/lib/rules");
+    }
+
+    #[test]
+    fn test_resolve_source_rejects_invalid_http_header() {
+        let paths = TestPaths {
+            root: PathBuf::from("unused"),
+        };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.3"));
+        let source_info = test_source_info("https://example.com/rules.tar.gz");
+        let mut enabled_source = EnabledSource::new("test/source".to_string());
+        enabled_source.http_header = Some("Authorization Bearer token".to_string());
+
+        assert!(manager
+            .resolve_source(&source_info, &enabled_source)
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_http_header() {
+        let (name, value) =
+            UpdateManager::parse_http_header("Authorization: Bearer abc:def").unwrap();
+        assert_eq!(name.as_str(), "authorization");
+        assert_eq!(value.to_str().unwrap(), "Bearer abc:def");
+    }
+
+    #[test]
+    fn test_process_source_offline_uses_cache() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+
+        let url = "https://example.com/test.rules";
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+        fs::write(
+            paths
+                .cache_dir()
+                .join(format!("{}.rules", UpdateManager::url_hash(url))),
+            "alert ip any any -> any any (msg:\"cached\"; sid:1;)\n",
+        )
+        .unwrap();
+
+        let request = |url: &str| super::SourceRequest {
+            url: url.to_string(),
+            display_url: url.to_string(),
+            http_header: None,
+            checksum: true,
+            public_key: None,
+        };
+        let processed = manager
+            .process_source(
+                "test",
+                &request(url),
+                &RuleConf::default(),
+                false,
+                true,
+                true,
+            )
+            .unwrap();
+        assert_eq!(processed.rules["1:1"].group, "test.rules");
+
+        let err = manager
+            .process_source(
+                "other",
+                &request("https://example.com/other.rules"),
+                &RuleConf::default(),
+                false,
+                true,
+                true,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot update offline"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_sources_keeps_order() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+
+        // More sources than workers, with one missing from the cache.
+        let requests: Vec<super::SourceRequest> = (0..10)
+            .map(|i| super::SourceRequest {
+                url: format!("https://example.com/{i}.rules"),
+                display_url: format!("https://example.com/{i}.rules"),
+                http_header: None,
+                checksum: false,
+                public_key: None,
+            })
+            .collect();
+        for request in requests
+            .iter()
+            .filter(|request| !request.url.contains("/7."))
+        {
+            fs::write(
+                paths
+                    .cache_dir()
+                    .join(format!("{}.rules", UpdateManager::url_hash(&request.url))),
+                "",
+            )
+            .unwrap();
+        }
+        let names: Vec<String> = (0..10).map(|i| format!("source-{i}")).collect();
+        let sources: Vec<(&str, &super::SourceRequest)> = names
+            .iter()
+            .map(String::as_str)
+            .zip(requests.iter())
+            .collect();
+
+        let archives = manager.fetch_sources(&sources, false, true, true);
+        assert_eq!(archives.len(), 10);
+        for (i, archive) in archives.iter().enumerate() {
+            match archive {
+                Ok(location) => assert_eq!(
+                    location,
+                    &SourceLocation::cached(paths.cache_dir().join(format!(
+                        "{}.rules",
+                        UpdateManager::url_hash(&requests[i].url)
+                    )))
+                ),
+                Err(err) => {
+                    assert_eq!(i, 7);
+                    assert!(err.to_string().contains("source-7"));
+                }
+            }
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_process_source_reads_local_directory() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+
+        let rules_dir = root.join("vetted");
+        fs::create_dir_all(rules_dir.join("dns")).unwrap();
+        fs::write(
+            rules_dir.join("dns").join("dns.rules"),
+            "alert dns any any -> any any (msg:\"test\"; dataset:isset,myset,type string,load lists/foo.lst; sid:1; rev:1;)\n",
+        )
+        .unwrap();
+        fs::create_dir_all(rules_dir.join("dns").join("lists")).unwrap();
+        fs::write(rules_dir.join("dns").join("lists").join("foo.lst"), "one\n").unwrap();
+
+        let request = super::SourceRequest {
+            url: format!("file://{}", rules_dir.display()),
+            display_url: format!("file://{}", rules_dir.display()),
+            http_header: None,
+            checksum: true,
+            public_key: None,
+        };
+        // Local sources are read in place, also when offline.
+        let processed = manager
+            .process_source("vetted", &request, &RuleConf::default(), false, true, true)
+            .unwrap();
+        let rule = &processed.rules["1:1"];
+        assert_eq!(rule.group, "dns/dns.rules");
+        assert_eq!(rule.datasets.len(), 1);
+        assert_eq!(rule.datasets[0].content, b"one\n");
+        assert!(!paths.cache_dir().exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_source_verifies_signature() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        let progress = super::DownloadProgress::new(true);
+
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("test.rules");
+        fs::write(&path, "test").unwrap();
+        let request = super::SourceRequest {
+            url: format!("file://{}", path.display()),
+            display_url: format!("file://{}", path.display()),
+            http_header: None,
+            checksum: false,
+            public_key: Some(crate::signature::tests::PUBLIC_KEY.to_string()),
+        };
+        let fetch = || manager.fetch_source("signed", &request, false, false, &progress);
+
+        assert!(fetch().is_err());
+        fs::write(
+            root.join("test.rules.sig"),
+            crate::signature::tests::SIGNATURE,
+        )
+        .unwrap();
+        assert!(fetch().is_ok());
+        fs::write(&path, "tampered").unwrap();
+        assert!(fetch().is_err());
+
+        // A download cached without the key is not used with it.
+        let unsigned = super::SourceRequest {
+            url: request.url.clone(),
+            display_url: request.url.clone(),
+            http_header: None,
+            checksum: false,
+            public_key: None,
+        };
+        assert_ne!(request.cache_key(), unsigned.cache_key());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 
-                if !file_type.is_file() {
-                    continue;
-                }
+    #[test]
+    fn test_fetch_source_verifies_cached_signature() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        let progress = super::DownloadProgress::new(true);
+
+        let request = super::SourceRequest {
+            url: "https://example.com/test.rules".to_string(),
+            display_url: "https://example.com/test.rules".to_string(),
+            http_header: None,
+            checksum: false,
+            public_key: Some(crate::signature::tests::PUBLIC_KEY.to_string()),
+        };
+        let cache_path = paths
+            .cache_dir()
+            .join(format!("{}.rules", request.cache_key()));
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+        fs::write(&cache_path, "test").unwrap();
+        let fetch = || manager.fetch_source("signed", &request, false, true, &progress);
+
+        // A cached download is only used offline with a valid signature.
+        assert!(fetch().is_err());
+        fs::write(
+            crate::signature::signature_path(&cache_path),
+            crate::signature::tests::SIGNATURE,
+        )
+        .unwrap();
+        assert!(fetch().is_ok());
+        fs::write(&cache_path, "tampered").unwrap();
+        let err = fetch().unwrap_err();
+        assert!(format!("{err:#}").contains("Refusing cached download of signed"));
 
-                let Ok(relative_path) = path.strip_prefix(&rules_dir).map(Path::to_path_buf) else {
-                    continue;
-                };
+        fs::remove_dir_all(&root).unwrap();
+    }
 
-                if !dataset_paths.contains(&relative_path)
-                    && Self::should_cleanup_dataset_path(&relative_path)
-                {
-                    fs::remove_file(&path).with_context(|| {
-                        format!("Failed to remove unreferenced dataset {}", path.display())
-                    })?;
-                }
-            }
-        }
+    #[test]
+    fn test_process_cached_source_falls_back_to_cache() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
 
-        dirs.sort_by_key(|dir| Reverse(dir.components().count()));
-        for dir in dirs {
-            let mut entries = fs::read_dir(&dir)
-                .with_context(|| format!("Failed to read dataset directory {}", dir.display()))?;
-            if entries.next().is_none() {
-                fs::remove_dir(&dir).with_context(|| {
-                    format!("Failed to remove empty dataset directory {}", dir.display())
-                })?;
-            }
-        }
+        let url = "https://example.com/test.rules";
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+        fs::write(
+            paths
+                .cache_dir()
+                .join(format!("{}.rules", UpdateManager::url_hash(url))),
+            "alert ip any any -> any any (msg:\"cached\"; sid:1;)\n",
+        )
+        .unwrap();
+
+        let request = |url: &str| super::SourceRequest {
+            url: url.to_string(),
+            display_url: url.to_string(),
+            http_header: None,
+            checksum: true,
+            public_key: None,
+        };
+        let processed = manager
+            .process_cached_source(
+                "test",
+                &request(url),
+                &RuleConf::default(),
+                anyhow::anyhow!("HTTP 503"),
+                true,
+            )
+            .unwrap();
+        assert!(processed.rules.contains_key("1:1"));
+
+        // Without a cached download the original error is returned.
+        let err = manager
+            .process_cached_source(
+                "other",
+                &request("https://example.com/other.rules"),
+                &RuleConf::default(),
+                anyhow::anyhow!("HTTP 503"),
+                true,
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "HTTP 503");
 
-        Ok(())
+        fs::remove_dir_all(&root).unwrap();
     }
 
-    fn get_output_path(&self) -> PathBuf {
-        self.path_provider.rules_dir().join(DEFAULT_OUTPUT_FILE)
+    #[test]
+    fn test_verify_checksum() {
+        let md5 = format!(
+            "{:x}",
+            md5::compute(b"alert ip any any -> any any (sid:1;)\n")
+        );
+        let truncated = format!("{:x}", md5::compute(b"truncated"));
+        assert!(UpdateManager::verify_checksum(&md5, &md5).is_ok());
+        assert!(UpdateManager::verify_checksum(&truncated, &md5).is_err());
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::{ResolvedDataset, Rule, RuleFilters, UpdateManager};
-    use crate::paths::PathProvider;
-    use std::collections::HashMap;
-    use std::fs;
-    use std::path::{Path, PathBuf};
-    use std::time::{SystemTime, UNIX_EPOCH};
 
-    struct TestPaths {
-        root: PathBuf,
+    #[test]
+    fn test_source_rules_filename() {
+        assert_eq!(
+            UpdateManager::source_rules_filename(
+                "local/test",
+                "https://example.com/a/emerging.rules"
+            ),
+            "emerging.rules"
+        );
+        assert_eq!(
+            UpdateManager::source_rules_filename(
+                "local/test",
+                "https://example.com/test.rules.gz?x=1"
+            ),
+            "test.rules"
+        );
+        assert_eq!(
+            UpdateManager::source_rules_filename("local/test", "https://example.com/download"),
+            "local-test.rules"
+        );
     }
 
-    impl PathProvider for TestPaths {
-        fn sources_dir(&self) -> PathBuf {
-            self.root.join("sources")
-        }
-
-        fn cache_dir(&self) -> PathBuf {
-            self.root.join("cache")
-        }
+    #[test]
+    fn test_parse_md5_checksum() {
+        assert_eq!(
+            UpdateManager::parse_md5_checksum("D41D8CD98F00B204E9800998ECF8427E  rules.tar.gz\n"),
+            Some("d41d8cd98f00b204e9800998ecf8427e".to_string())
+        );
+        assert_eq!(UpdateManager::parse_md5_checksum("<html>"), None);
+        assert_eq!(UpdateManager::parse_md5_checksum(""), None);
+    }
 
-        fn rules_dir(&self) -> PathBuf {
-            self.root.join("rules")
-        }
+    #[test]
+    fn test_parse_rules() {
+        let paths = TestPaths {
+            root: PathBuf::from("unused"),
+        };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        let content = br#"# A comment mentioning sid:1;
+alert tcp any any -> any any (msg:"sid:999 in msg"; content:"rev:9"; sid:100; rev:2;)
+# alert udp any any -> any any (msg:"disabled"; sid:200; gid:3;)
+rejectboth tcp any any -> any any (msg:"multi"; \
+    flow:established; \
+    sid:300;)
+alert tcp any any -> any any (msg:"missing sid";)
+"#;
+
+        let rules = manager.parse_rules("rules/test.rules", content).unwrap();
+        assert_eq!(rules.len(), 3);
+
+        assert_eq!(rules[0].sid, 100);
+        assert_eq!(rules[0].rev, 2);
+        assert_eq!(rules[0].msg, "sid:999 in msg");
+        assert!(rules[0].enabled);
+
+        assert_eq!((rules[1].gid, rules[1].sid), (3, 200));
+        assert!(!rules[1].enabled);
+        assert!(rules[1].raw.starts_with("alert udp"));
+
+        assert_eq!(rules[2].action, "rejectboth");
+        assert_eq!(rules[2].sid, 300);
+        assert_eq!(
+            rules[2].raw,
+            r#"rejectboth tcp any any -> any any (msg:"multi"; flow:established; sid:300;)"#
+        );
     }
 
     #[test]
@@ -1016,6 +2769,8 @@ mod tests {
         let rule = Rule {
             raw: r#"alert dns any any -> any any (msg:"test"; dataset:isset,myset,type string,load foo.lst; sid:1; rev:1;)"#.to_string(),
             enabled: true,
+            action: "alert".to_string(),
+            options: Vec::new(),
             sid: 1,
             gid: 1,
             rev: 1,
@@ -1049,6 +2804,8 @@ mod tests {
         let rule = Rule {
             raw: r#"alert dns any any -> any any (msg:"test"; dataset:isset,myset,type string,load /etc/shadow; sid:1; rev:1;)"#.to_string(),
             enabled: true,
+            action: "alert".to_string(),
+            options: Vec::new(),
             sid: 1,
             gid: 1,
             rev: 1,
@@ -1071,6 +2828,8 @@ mod tests {
         let rule = Rule {
             raw: r#"alert dns any any -> any any (msg:"test"; dataset:isset,myset,type string,load foo.lst; dataset:isset,otherset,type string,load /etc/shadow; sid:1; rev:1;)"#.to_string(),
             enabled: true,
+            action: "alert".to_string(),
+            options: Vec::new(),
             sid: 1,
             gid: 1,
             rev: 1,
@@ -1099,6 +2858,39 @@ mod tests {
         assert!(rewritten.contains("load /etc/shadow"));
     }
 
+    #[test]
+    fn test_missing_dataset_rule_not_enabled_for_flowbits() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        fs::create_dir_all(&root).unwrap();
+        let rules_path = root.join("local.rules");
+        fs::write(
+            &rules_path,
+            concat!(
+                "alert dns any any -> any any (msg:\"checker\"; flowbits:isset,seen; sid:1; rev:1;)\n",
+                "# alert dns any any -> any any (msg:\"setter\"; flowbits:set,seen; dataset:isset,myset,type string,load missing.lst; sid:2; rev:1;)\n",
+            ),
+        )
+        .unwrap();
+
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        let mut rules = manager
+            .process_local(&[rules_path], &RuleConf::default(), true)
+            .unwrap()
+            .rules;
+
+        // The setter is dropped rather than enabled with a dataset that is
+        // never written.
+        assert!(!rules.contains_key("1:2"));
+        assert!(crate::flowbits::resolve_flowbits(&mut rules, &HashSet::new()).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
     #[test]
     fn test_collect_dataset_files_uses_winning_rule() {
         let mut rules = HashMap::new();
@@ -1111,6 +2903,8 @@ mod tests {
         let old_rule = Rule {
             raw: "alert ip any any -> any any (msg:\"old\"; sid:100; rev:1;)".to_string(),
             enabled: true,
+            action: "alert".to_string(),
+            options: Vec::new(),
             sid: 100,
             gid: 1,
             rev: 1,
@@ -1125,6 +2919,8 @@ mod tests {
         let new_rule = Rule {
             raw: "alert ip any any -> any any (msg:\"new\"; sid:100; rev:2;)".to_string(),
             enabled: true,
+            action: "alert".to_string(),
+            options: Vec::new(),
             sid: 100,
             gid: 1,
             rev: 2,
@@ -1157,6 +2953,8 @@ mod tests {
                 Rule {
                     raw: "alert ip any any -> any any (msg:\"keep\"; sid:100; rev:1;)".to_string(),
                     enabled: true,
+                    action: "alert".to_string(),
+                    options: Vec::new(),
                     sid: 100,
                     gid: 1,
                     rev: 1,
@@ -1171,6 +2969,8 @@ mod tests {
                     raw: "alert ip any any -> any any (msg:\"block me\"; sid:200; rev:1;)"
                         .to_string(),
                     enabled: true,
+                    action: "alert".to_string(),
+                    options: Vec::new(),
                     sid: 200,
                     gid: 1,
                     rev: 1,
@@ -1201,6 +3001,8 @@ mod tests {
                 Rule {
                     raw: "alert ip any any -> any any (msg:\"keep\"; sid:100; rev:1;)".to_string(),
                     enabled: true,
+                    action: "alert".to_string(),
+                    options: Vec::new(),
                     sid: 100,
                     gid: 1,
                     rev: 1,
@@ -1215,6 +3017,8 @@ mod tests {
                     raw: "alert ip any any -> any any (msg:\"drop me\"; sid:200; rev:1;)"
                         .to_string(),
                     enabled: true,
+                    action: "alert".to_string(),
+                    options: Vec::new(),
                     sid: 200,
                     gid: 1,
                     rev: 1,
@@ -1241,6 +3045,183 @@ mod tests {
             .contains("Invalid --disable-regex pattern: ("));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_test_rules_reports_suricata_failure() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        fs::create_dir_all(&root).unwrap();
+        let paths = TestPaths { root: root.clone() };
+
+        let rules_path = root.join("suricata.rules.tmp");
+        fs::write(&rules_path, b"alert ip any any -> any any (sid:1;)\n").unwrap();
+        let conf = root.join("suricata.yaml");
+        fs::write(&conf, b"%YAML 1.1\n").unwrap();
+
+        let suricata = root.join("suricata");
+        fs::write(
+            &suricata,
+            "#!/bin/sh\necho \"Error: bad rule at line 1\" >&2\nexit 1\n",
+        )
+        .unwrap();
+        fs::set_permissions(&suricata, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let manager = UpdateManager::new_with_suricata(&paths, Some(&suricata), Some("7.0.0"));
+        let err = manager
+            .test_rules(&rules_path, Some(&conf), true)
+            .unwrap_err();
+        assert!(err.to_string().contains("bad rule at line 1"));
+
+        // A Suricata binary or configuration that was given must exist.
+        let missing = UpdateManager::new_with_suricata(
+            &paths,
+            Some(&root.join("missing-suricata")),
+            Some("7.0.0"),
+        );
+        assert!(missing.test_rules(&rules_path, Some(&conf), true).is_err());
+        assert!(manager
+            .test_rules(&rules_path, Some(&root.join("missing.yaml")), true)
+            .is_err());
+
+        // Without the default configuration the test is skipped.
+        assert!(manager.test_rules(&rules_path, None, true).is_ok());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_files_differ() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        fs::create_dir_all(&root).unwrap();
+
+        let new = root.join("new.rules");
+        let old = root.join("old.rules");
+        fs::write(&new, b"alert ip any any -> any any (sid:1;)\n").unwrap();
+        assert!(UpdateManager::files_differ(&new, &old).unwrap());
+
+        fs::write(&old, b"alert ip any any -> any any (sid:1;)\n").unwrap();
+        assert!(!UpdateManager::files_differ(&new, &old).unwrap());
+
+        fs::write(&old, b"alert ip any any -> any any (sid:2;)\n").unwrap();
+        assert!(UpdateManager::files_differ(&new, &old).unwrap());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_process_local_resolves_datasets_relative_to_rule_file() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let local_dir = root.join("local");
+        fs::create_dir_all(local_dir.join("lists")).unwrap();
+        fs::write(
+            local_dir.join("local.rules"),
+            concat!(
+                "alert http any any -> any any (msg:\"local\"; http.host; dataset:isset,hosts,type string,load lists/hosts.lst; sid:9000001; rev:1;)\n",
+                "alert http any any -> any any (msg:\"missing\"; http.host; dataset:isset,other,type string,load lists/missing.lst; sid:9000002; rev:1;)\n",
+            ),
+        )
+        .unwrap();
+        fs::write(
+            local_dir.join("lists").join("hosts.lst"),
+            b"ZXhhbXBsZS5jb20=\n",
+        )
+        .unwrap();
+
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        let processed = manager
+            .process_local(&[local_dir], &RuleConf::default(), true)
+            .unwrap();
+
+        assert_eq!(processed.rules.len(), 1);
+        let rule = &processed.rules["1:9000001"];
+        assert_eq!(rule.datasets.len(), 1);
+        assert_eq!(rule.datasets[0].content, b"ZXhhbXBsZS5jb20=\n");
+        assert!(rule.raw.contains(&format!(
+            "load {}",
+            UpdateManager::path_to_rule_string(&rule.datasets[0].output_path)
+        )));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_process_source_files_reads_datasets_after_rules() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        fs::create_dir_all(&root).unwrap();
+
+        // The dataset comes after the rule file that loads it.
+        let entries: [(&str, &[u8]); 3] = [
+            (
+                "rules/test.rules",
+                b"alert dns any any -> any any (msg:\"test\"; dataset:isset,myset,type string,load foo.lst; sid:1; rev:1;)\n",
+            ),
+            ("rules/deleted.rules", b"alert ip any any -> any any (msg:\"deleted\"; sid:2; rev:1;)\n"),
+            ("rules/foo.lst", b"one\ntwo\n"),
+        ];
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, content).unwrap();
+        }
+        let archive_path = root.join("archive.tar.gz");
+        fs::write(
+            &archive_path,
+            builder.into_inner().unwrap().finish().unwrap(),
+        )
+        .unwrap();
+
+        let request = super::SourceRequest {
+            url: "https://example.com/rules.tar.gz".to_string(),
+            display_url: "https://example.com/rules.tar.gz".to_string(),
+            http_header: None,
+            checksum: false,
+            public_key: None,
+        };
+        let rule_conf = RuleConf::default()
+            .with_ignore(&["*deleted.rules".to_string()])
+            .unwrap();
+        let processed = manager
+            .process_source_files(
+                "et/open",
+                &request,
+                &SourceLocation::cached(archive_path),
+                &rule_conf,
+                true,
+            )
+            .unwrap();
+
+        assert_eq!(processed.rules.len(), 1);
+        let rule = &processed.rules["1:1"];
+        assert_eq!(rule.datasets.len(), 1);
+        assert_eq!(rule.datasets[0].content, b"one\ntwo\n");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
     #[test]
     fn test_write_dataset_files_only_cleans_managed_datasets() {
         let unique = SystemTime::now()
@@ -1262,7 +3243,10 @@ mod tests {
         let mut dataset_files = HashMap::new();
         dataset_files.insert(keep_rel.clone(), b"keep".to_vec());
 
-        manager.write_dataset_files(&dataset_files).unwrap();
+        UpdateManager::write_dataset_contents(&paths.rules_dir(), &dataset_files).unwrap();
+        manager
+            .cleanup_unreferenced_dataset_files(&dataset_files.keys().collect())
+            .unwrap();
 
         assert_eq!(
             fs::read(paths.rules_dir().join(&keep_rel)).unwrap(),
@@ -1273,4 +3257,196 @@ mod tests {
 
         fs::remove_dir_all(&root).unwrap();
     }
+
+    /// Write a cached default index with et/open at the given URL, a rules
+    /// directory lets an offline update run without the network.
+    fn write_test_index(paths: &TestPaths, url: &str) {
+        let mut index = SourceIndex {
+            version: 1,
+            ..Default::default()
+        };
+        index
+            .sources
+            .insert("et/open".to_string(), test_source_info(url));
+        fs::create_dir_all(paths.cache_dir()).unwrap();
+        fs::write(
+            paths.cache_dir().join("index.yaml"),
+            serde_yaml::to_string(&index).unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_update_fails_on_local_rules_error() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let source_dir = root.join("source");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(
+            source_dir.join("test.rules"),
+            "alert ip any any -> any any (msg:\"test\"; sid:1; rev:1;)\n",
+        )
+        .unwrap();
+        // The default index is remote, so the local URL is configured on
+        // the enabled source.
+        let url = format!("file://{}", source_dir.display());
+        write_test_index(&paths, "https://example.com/rules.tar.gz");
+        fs::create_dir_all(paths.sources_dir()).unwrap();
+        fs::write(
+            paths.sources_dir().join("et-open.yaml"),
+            format!("source: et/open\nurl: {url}\n"),
+        )
+        .unwrap();
+
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        let options = UpdateOptions {
+            quiet: true,
+            offline: true,
+            no_test: true,
+            local: vec![root.join("missing.rules")],
+            ..Default::default()
+        };
+        let err = manager.update_with_options(&options).unwrap_err();
+        assert!(format!("{err:#}").contains("local rules"));
+        assert!(!paths.rules_dir().join("suricata.rules").exists());
+
+        // Without the missing local path the update succeeds.
+        let options = UpdateOptions {
+            local: Vec::new(),
+            ..options
+        };
+        manager.update_with_options(&options).unwrap();
+        let rules = fs::read_to_string(paths.rules_dir().join("suricata.rules")).unwrap();
+        assert!(rules.contains("sid:1;"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_offline_update_fails_without_cached_source() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        write_test_index(&paths, "https://example.com/rules.tar.gz");
+        fs::create_dir_all(paths.rules_dir()).unwrap();
+        let output_path = paths.rules_dir().join("suricata.rules");
+        fs::write(&output_path, "existing\n").unwrap();
+
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        let err = manager
+            .update_with_options(&UpdateOptions {
+                quiet: true,
+                offline: true,
+                no_test: true,
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("No cached download of et/open"));
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), "existing\n");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rule_report_from_previous_output() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("suricasta-rules-test-{unique}"));
+        let paths = TestPaths { root: root.clone() };
+        let manager = UpdateManager::new_with_suricata_version(&paths, Some("7.0.0"));
+        let output_path = paths.rules_dir().join("suricata.rules");
+
+        let rules = |rules: &[(&str, bool)]| -> HashMap<String, Rule> {
+            rules
+                .iter()
+                .map(|(raw, enabled)| {
+                    let rule = Rule::parse(raw, *enabled, "test.rules").unwrap();
+                    (format!("{}:{}", rule.gid, rule.sid), rule)
+                })
+                .collect()
+        };
+
+        let old = rules(&[
+            (
+                "alert ip any any -> any any (msg:\"kept\"; sid:1; rev:1;)",
+                true,
+            ),
+            (
+                "alert ip any any -> any any (msg:\"bumped\"; sid:2; rev:1;)",
+                true,
+            ),
+            (
+                "alert ip any any -> any any (msg:\"removed\"; sid:3; rev:1;)",
+                true,
+            ),
+            (
+                "alert ip any any -> any any (msg:\"disabled\"; sid:4; rev:1;)",
+                true,
+            ),
+            (
+                "alert ip any any -> any any (msg:\"enabled\"; sid:5; rev:1;)",
+                false,
+            ),
+        ]);
+        manager.write_rules(&old, &output_path).unwrap();
+        let previous = manager.load_previous_rules(&output_path).unwrap();
+        assert!(!previous["1:5"].enabled);
+
+        let new = rules(&[
+            (
+                "alert ip any any -> any any (msg:\"kept\"; sid:1; rev:1;)",
+                true,
+            ),
+            (
+                "alert ip any any -> any any (msg:\"bumped\"; sid:2; rev:2;)",
+                true,
+            ),
+            (
+                "alert ip any any -> any any (msg:\"disabled\"; sid:4; rev:1;)",
+                false,
+            ),
+            (
+                "alert ip any any -> any any (msg:\"enabled\"; sid:5; rev:1;)",
+                true,
+            ),
+            (
+                "alert ip any any -> any any (msg:\"added\"; sid:6; rev:1;)",
+                true,
+            ),
+            (
+                "alert ip any any -> any any (msg:\"new disabled\"; sid:7; rev:1;)",
+                false,
+            ),
+        ]);
+
+        let report = RuleReport::new(&previous, &new);
+        let sids = |changes: &[RuleChange]| changes.iter().map(|c| c.sid).collect::<Vec<_>>();
+
+        assert_eq!(sids(&report.added), vec![6]);
+        assert_eq!(sids(&report.removed), vec![3]);
+        assert_eq!(sids(&report.modified), vec![2]);
+        assert_eq!(report.modified[0].previous_rev, Some(1));
+        assert_eq!(sids(&report.enabled), vec![5]);
+        assert_eq!(sids(&report.disabled), vec![4]);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["modified"][0]["previous-rev"], 1);
+        assert_eq!(json["added"][0]["msg"], "added");
+
+        // Writing the new rules and loading them back reports no changes.
+        manager.write_rules(&new, &output_path).unwrap();
+        let previous = manager.load_previous_rules(&output_path).unwrap();
+        assert!(RuleReport::new(&previous, &new).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 }